@@ -0,0 +1,186 @@
+//! On-disk response cache
+//!
+//! Responses are stored as one JSON file per request, named after a SHA-256
+//! hash of the full `ChatRequest`. Entries expire after a TTL and the least
+//! recently used ones are evicted when the cache grows past its size cap.
+
+use crate::error::Result;
+use crate::providers::ProviderType;
+use crate::types::{ChatRequest, ChatResponse};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// Entries older than this are ignored unless configured otherwise
+pub const DEFAULT_TTL: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// Size the cache is trimmed to unless configured otherwise
+pub const DEFAULT_MAX_BYTES: u64 = 50 * 1024 * 1024;
+
+/// How the gateway uses the response cache
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CacheMode {
+    /// Serve hits and store misses
+    Use,
+    /// Skip lookups but store fresh responses
+    Refresh,
+    /// Neither read nor write the cache
+    #[default]
+    Bypass,
+}
+
+/// Cached response together with the request hash it answers
+#[derive(Debug, Serialize, Deserialize)]
+struct CacheEntry {
+    key: String,
+    response: ChatResponse,
+}
+
+/// Content-addressed cache of chat responses
+#[derive(Debug, Clone)]
+pub struct ResponseCache {
+    dir: PathBuf,
+    ttl: Duration,
+    max_bytes: u64,
+}
+
+impl ResponseCache {
+    /// Create a cache stored in `dir`
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            ttl: DEFAULT_TTL,
+            max_bytes: DEFAULT_MAX_BYTES,
+        }
+    }
+
+    /// Set how long entries stay valid
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Set the size the cache is trimmed to
+    pub fn with_max_bytes(mut self, max_bytes: u64) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    /// Default cache location (`<cache dir>/fuckmit/responses`)
+    pub fn default_dir() -> Option<PathBuf> {
+        dirs::cache_dir().map(|dir| dir.join("fuckmit").join("responses"))
+    }
+
+    /// Directory the entries are stored in
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Cache key of a request sent to a provider
+    pub fn key(provider_type: &ProviderType, request: &ChatRequest) -> Result<String> {
+        let mut hasher = Sha256::new();
+        hasher.update(provider_type.to_string().as_bytes());
+        hasher.update([0]);
+        hasher.update(serde_json::to_vec(request)?);
+
+        Ok(hasher
+            .finalize()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect())
+    }
+
+    /// Look up a fresh entry, marking it as recently used
+    pub fn get(&self, key: &str) -> Option<ChatResponse> {
+        let path = self.entry_path(key);
+        let modified = fs::metadata(&path).and_then(|m| m.modified()).ok()?;
+        if modified.elapsed().unwrap_or_default() > self.ttl {
+            let _ = fs::remove_file(&path);
+            return None;
+        }
+
+        let entry: CacheEntry = serde_json::from_slice(&fs::read(&path).ok()?).ok()?;
+        if entry.key != key {
+            return None;
+        }
+
+        // The access time drives LRU eviction; the TTL counts from the last write
+        let _ = File::open(&path)
+            .and_then(|file| file.set_times(fs::FileTimes::new().set_accessed(SystemTime::now())));
+
+        let mut response = entry.response;
+        response.cached = true;
+        Some(response)
+    }
+
+    /// Store a response and evict old entries if the cache is over its size cap
+    pub fn put(&self, key: &str, response: &ChatResponse) -> Result<()> {
+        fs::create_dir_all(&self.dir)?;
+
+        let entry = CacheEntry {
+            key: key.to_string(),
+            response: ChatResponse {
+                cached: false,
+                ..response.clone()
+            },
+        };
+
+        // Write to a temporary file first so readers never see a partial entry
+        let path = self.entry_path(key);
+        let temp = path.with_extension("tmp");
+        fs::write(&temp, serde_json::to_vec(&entry)?)?;
+        fs::rename(&temp, &path)?;
+
+        self.evict()
+    }
+
+    /// Remove every entry
+    pub fn clear(&self) -> Result<()> {
+        match fs::remove_dir_all(&self.dir) {
+            Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err.into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Drop expired entries, then least recently used ones until under the size cap
+    fn evict(&self) -> Result<()> {
+        let mut entries = Vec::new();
+        let mut total = 0;
+
+        for entry in fs::read_dir(&self.dir)? {
+            let entry = entry?;
+            let metadata = entry.metadata()?;
+            if !metadata.is_file() {
+                continue;
+            }
+
+            let modified = metadata.modified()?;
+            if modified.elapsed().unwrap_or_default() > self.ttl {
+                let _ = fs::remove_file(entry.path());
+                continue;
+            }
+
+            let used = metadata.accessed().unwrap_or(modified).max(modified);
+            total += metadata.len();
+            entries.push((used, metadata.len(), entry.path()));
+        }
+
+        entries.sort_by_key(|(used, _, _)| *used);
+        for (_, len, path) in entries {
+            if total <= self.max_bytes {
+                break;
+            }
+            if fs::remove_file(path).is_ok() {
+                total -= len;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn entry_path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", key))
+    }
+}
@@ -0,0 +1,314 @@
+//! Record and replay of provider HTTP traffic
+//!
+//! With a cassette configured, every provider request goes through a JSON
+//! file of recorded exchanges instead of, or on the way to, the network.
+//! Requests are matched on method, URL and body; JSON bodies are compared
+//! regardless of key order. API keys are never written: auth headers are not
+//! recorded, and credential query parameters and JSON fields are redacted
+//! from URLs and bodies before matching and saving.
+
+use crate::error::{GatewayError, Result};
+use crate::providers::http::request_error;
+use crate::redact;
+use reqwest::{Client, Request, Response};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+
+/// Cassettes in use, shared by every provider that records to the same file
+static OPEN: Mutex<BTreeMap<PathBuf, Arc<Cassette>>> = Mutex::new(BTreeMap::new());
+
+/// Response headers that no longer apply once the body is stored decoded and redacted
+const SKIPPED_HEADERS: &[&str] = &["content-length", "transfer-encoding", "connection"];
+
+/// How a cassette is used
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CassetteMode {
+    /// Replay recorded exchanges and record requests that have none
+    #[default]
+    Auto,
+    /// Replay recorded exchanges and fail requests that have none
+    Replay,
+    /// Call the providers and record every exchange, replacing the file
+    Record,
+}
+
+impl fmt::Display for CassetteMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CassetteMode::Auto => write!(f, "auto"),
+            CassetteMode::Replay => write!(f, "replay"),
+            CassetteMode::Record => write!(f, "record"),
+        }
+    }
+}
+
+impl FromStr for CassetteMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "auto" => Ok(CassetteMode::Auto),
+            "replay" => Ok(CassetteMode::Replay),
+            "record" => Ok(CassetteMode::Record),
+            _ => Err(format!("Unknown cassette mode: {}", s)),
+        }
+    }
+}
+
+/// Cassette file and how to use it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CassetteConfig {
+    /// JSON file the exchanges are recorded in
+    pub path: PathBuf,
+    /// Whether to replay, record or both
+    #[serde(default)]
+    pub mode: CassetteMode,
+}
+
+impl CassetteConfig {
+    /// Use the cassette at `path` in auto mode
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            mode: CassetteMode::default(),
+        }
+    }
+
+    /// Set how the cassette is used
+    pub fn with_mode(mut self, mode: CassetteMode) -> Self {
+        self.mode = mode;
+        self
+    }
+}
+
+/// Contents of a cassette file
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CassetteFile {
+    interactions: Vec<Interaction>,
+}
+
+/// A recorded request and the response it got
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Interaction {
+    request: RecordedRequest,
+    response: RecordedResponse,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct RecordedRequest {
+    method: String,
+    url: String,
+    #[serde(default)]
+    body: Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RecordedResponse {
+    status: u16,
+    #[serde(default)]
+    headers: BTreeMap<String, String>,
+    #[serde(default)]
+    body: Value,
+}
+
+/// An open cassette
+pub(crate) struct Cassette {
+    path: PathBuf,
+    mode: CassetteMode,
+    /// Recorded interactions and whether each was replayed already
+    interactions: Mutex<Vec<(Interaction, bool)>>,
+}
+
+impl Cassette {
+    /// Open the cassette described by `config`, sharing it with other providers using the same file
+    ///
+    /// A file can only be used in one mode at a time.
+    pub(crate) fn open(config: &CassetteConfig) -> Result<Arc<Self>> {
+        let mut open = OPEN.lock().unwrap_or_else(|err| err.into_inner());
+        if let Some(cassette) = open.get(&config.path) {
+            if cassette.mode != config.mode {
+                return Err(GatewayError::Config(format!(
+                    "Cassette {} is already open in {} mode, not {}",
+                    config.path.display(),
+                    cassette.mode,
+                    config.mode
+                )));
+            }
+            return Ok(cassette.clone());
+        }
+
+        let interactions = match config.mode {
+            CassetteMode::Record => Vec::new(),
+            CassetteMode::Replay => load(&config.path)?.ok_or_else(|| {
+                GatewayError::Config(format!("Cassette {} does not exist", config.path.display()))
+            })?,
+            CassetteMode::Auto => load(&config.path)?.unwrap_or_default(),
+        };
+
+        let cassette = Arc::new(Self {
+            path: config.path.clone(),
+            mode: config.mode,
+            interactions: Mutex::new(interactions.into_iter().map(|i| (i, false)).collect()),
+        });
+        open.insert(config.path.clone(), cassette.clone());
+        Ok(cassette)
+    }
+
+    /// Answer `request` from the cassette, or send it and record the exchange
+    ///
+    /// Recorded responses are read in full before they are returned, so
+    /// streams are delivered in one piece while recording.
+    pub(crate) async fn execute(
+        &self,
+        provider: &str,
+        client: &Client,
+        request: Request,
+    ) -> Result<Response> {
+        let recorded = RecordedRequest::from_request(&request);
+
+        if self.mode != CassetteMode::Record {
+            if let Some(response) = self.replay(&recorded) {
+                return response.into_response();
+            }
+            if self.mode == CassetteMode::Replay {
+                return Err(GatewayError::InvalidRequest(format!(
+                    "No exchange in cassette {} matches {} {}",
+                    self.path.display(),
+                    recorded.method,
+                    recorded.url
+                )));
+            }
+        }
+
+        let started = std::time::Instant::now();
+        let response = client
+            .execute(request)
+            .await
+            .map_err(|err| request_error(provider, started, err))?;
+        let status = response.status().as_u16();
+        let headers = redact::headers(response.headers())
+            .into_iter()
+            .filter(|(name, _)| !SKIPPED_HEADERS.contains(&name.as_str()))
+            .collect();
+        let body = response
+            .bytes()
+            .await
+            .map_err(|err| request_error(provider, started, err))?;
+
+        let recorded_response = RecordedResponse {
+            status,
+            headers,
+            body: body_value(&body),
+        };
+        self.record(Interaction {
+            request: recorded,
+            response: recorded_response.clone(),
+        })?;
+        recorded_response.into_response()
+    }
+
+    /// First matching interaction not replayed yet, or the last match when all were
+    fn replay(&self, request: &RecordedRequest) -> Option<RecordedResponse> {
+        let mut interactions = self
+            .interactions
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+        let matches: Vec<usize> = (0..interactions.len())
+            .filter(|&index| &interactions[index].0.request == request)
+            .collect();
+        let index = *matches
+            .iter()
+            .find(|&&index| !interactions[index].1)
+            .or(matches.last())?;
+
+        interactions[index].1 = true;
+        Some(interactions[index].0.response.clone())
+    }
+
+    /// Append an interaction and rewrite the file
+    fn record(&self, interaction: Interaction) -> Result<()> {
+        let mut interactions = self
+            .interactions
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+        interactions.push((interaction, true));
+
+        let file = CassetteFile {
+            interactions: interactions.iter().map(|(i, _)| i.clone()).collect(),
+        };
+        if let Some(parent) = self.path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        let temp = self.path.with_extension("tmp");
+        fs::write(&temp, serde_json::to_vec_pretty(&file)?)?;
+        fs::rename(&temp, &self.path)?;
+        Ok(())
+    }
+}
+
+impl RecordedRequest {
+    /// Method, redacted URL and normalized body of `request`
+    fn from_request(request: &Request) -> Self {
+        let body = request
+            .body()
+            .and_then(|body| body.as_bytes())
+            .map(body_value)
+            .unwrap_or(Value::Null);
+
+        Self {
+            method: request.method().to_string(),
+            url: redact::url(request.url()),
+            body,
+        }
+    }
+}
+
+impl RecordedResponse {
+    /// Rebuild the HTTP response
+    fn into_response(self) -> Result<Response> {
+        let mut builder = ::http::Response::builder().status(self.status);
+        for (name, value) in &self.headers {
+            builder = builder.header(name, value);
+        }
+        let body = match self.body {
+            Value::String(text) => text,
+            Value::Null => String::new(),
+            value => value.to_string(),
+        };
+        let response = builder.body(body).map_err(|err| {
+            GatewayError::InvalidResponse(format!("Invalid recorded response: {}", err))
+        })?;
+        Ok(Response::from(response))
+    }
+}
+
+/// A body as redacted JSON, or as text when it is not JSON
+fn body_value(bytes: &[u8]) -> Value {
+    match serde_json::from_slice::<Value>(bytes) {
+        Ok(mut value) => {
+            redact::value(&mut value);
+            value
+        }
+        Err(_) => Value::String(String::from_utf8_lossy(bytes).into_owned()),
+    }
+}
+
+/// Read a cassette file, or `None` if it does not exist
+fn load(path: &Path) -> Result<Option<Vec<Interaction>>> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err.into()),
+    };
+    let file: CassetteFile = serde_json::from_str(&text).map_err(|err| {
+        GatewayError::Config(format!("Invalid cassette {}: {}", path.display(), err))
+    })?;
+    Ok(Some(file.interactions))
+}
@@ -1,4 +1,5 @@
-use clap::Parser;
+use clap::{ArgAction, Args, Parser, Subcommand};
+use tracing_subscriber::EnvFilter;
 
 #[derive(Parser)]
 #[command(name = "fuckmit")]
@@ -9,7 +10,7 @@ pub struct Cli {
     #[arg(short, long)]
     pub dry_run: bool,
 
-    /// Specify which AI model to use (openai, azure, deepseek, qwen) or provider/model format (e.g., openai/gpt-4)
+    /// Specify which AI model to use (openai, azure, deepseek, qwen, anthropic, gemini, ollama, mock or a custom provider name) or provider/model format (e.g., openai/gpt-4)
     #[arg(short, long)]
     pub model: Option<String>,
 
@@ -21,21 +22,142 @@ pub struct Cli {
     #[arg(short, long)]
     pub context: Option<String>,
 
-    /// Maximum number of tokens for the generated message
-    #[arg(long, default_value = "8192")]
-    pub max_tokens: u32,
+    /// Maximum number of tokens for the generated message [default: 8192]
+    #[arg(long)]
+    pub max_tokens: Option<u32>,
 
-    /// Temperature for AI generation (0.0 to 2.0)
-    #[arg(long, default_value = "0.7")]
-    pub temperature: f32,
+    /// Temperature for AI generation (0.0 to 2.0) [default: 0.7]
+    #[arg(long)]
+    pub temperature: Option<f32>,
+
+    /// Generate several commit messages and choose one
+    #[arg(
+        short = 'n',
+        long,
+        value_name = "N",
+        default_value = "1",
+        value_parser = clap::value_parser!(u32).range(1..=10)
+    )]
+    pub candidates: u32,
+
+    /// Use a named profile from the config file (also FUCKMIT_PROFILE)
+    #[arg(short, long)]
+    pub profile: Option<String>,
+
+    /// Always call the provider and don't cache the response
+    #[arg(long, conflicts_with = "refresh")]
+    pub no_cache: bool,
+
+    /// Ignore cached responses but cache the new one
+    #[arg(long)]
+    pub refresh: bool,
+
+    /// Log provider calls to stderr (-vv for HTTP details, -vvv for redacted bodies)
+    #[arg(short, long, action = ArgAction::Count, global = true)]
+    pub verbose: u8,
+
+    #[command(subcommand)]
+    pub command: Option<Commands>,
+}
+
+#[derive(Subcommand)]
+pub enum Commands {
+    /// Report token usage and cost by day, model and repository
+    Usage(UsageArgs),
+    /// Manage API keys in the encrypted credentials file
+    Credentials(CredentialsArgs),
+    /// Serve an OpenAI-compatible API backed by the configured providers
+    Serve(ServeArgs),
+    /// List the models offered by the configured providers
+    Models(ModelsArgs),
+}
+
+#[derive(Args)]
+pub struct UsageArgs {
+    /// Only include calls from the last N days (0 for all)
+    #[arg(long, default_value = "30")]
+    pub days: u32,
+
+    /// Price table file (defaults to ~/.config/fuckmit/prices.toml)
+    #[arg(long)]
+    pub prices: Option<std::path::PathBuf>,
+}
+
+#[derive(Args)]
+pub struct ServeArgs {
+    /// Port to listen on
+    #[arg(long, default_value = "8080")]
+    pub port: u16,
+
+    /// Address to listen on
+    #[arg(long, default_value = "127.0.0.1")]
+    pub host: std::net::IpAddr,
+
+    /// Answer repeated requests from the response cache (except sampled requests and tool calls)
+    #[arg(long)]
+    pub cache: bool,
+}
+
+#[derive(Args)]
+pub struct ModelsArgs {
+    /// Only list the models of this provider
+    pub provider: Option<String>,
+}
+
+#[derive(Args)]
+pub struct CredentialsArgs {
+    #[command(subcommand)]
+    pub command: CredentialsCommand,
+}
+
+#[derive(Subcommand)]
+pub enum CredentialsCommand {
+    /// Store an API key, read from the terminal or stdin
+    Set {
+        /// Name to refer to the key by (e.g. openai)
+        name: String,
+    },
+    /// Remove a stored API key
+    Remove {
+        /// Name of the key
+        name: String,
+    },
+    /// List the names of the stored API keys
+    List,
 }
 
 impl Cli {
     pub async fn execute(&self) -> anyhow::Result<()> {
-        // Import the generate module
-        use super::generate;
+        self.init_logging();
+
+        // Import the command modules
+        use super::{credentials, generate, models, serve, usage};
+
+        match &self.command {
+            Some(Commands::Usage(args)) => usage::show_usage(self, args),
+            Some(Commands::Credentials(args)) => credentials::manage_credentials(args),
+            Some(Commands::Serve(args)) => serve::serve(self, args).await,
+            Some(Commands::Models(args)) => models::list_models(self, args).await,
+            // Execute the generate command with the provided options
+            None => generate::generate_commit(self).await,
+        }
+    }
+
+    /// Set up logging to stderr from FUCKMIT_LOG or the verbosity flags
+    fn init_logging(&self) {
+        let filter = match std::env::var("FUCKMIT_LOG") {
+            Ok(directives) if !directives.trim().is_empty() => EnvFilter::new(directives),
+            _ => match self.verbose {
+                0 => return,
+                1 => EnvFilter::new("fuckmit=info"),
+                2 => EnvFilter::new("fuckmit=debug"),
+                _ => EnvFilter::new("fuckmit=trace"),
+            },
+        };
 
-        // Execute the generate command with the provided options
-        generate::generate_commit(self).await
+        let _ = tracing_subscriber::fmt()
+            .with_env_filter(filter)
+            .with_writer(std::io::stderr)
+            .try_init();
     }
 }
@@ -0,0 +1,85 @@
+use crate::commands::cli::{CredentialsArgs, CredentialsCommand};
+use crate::credentials::{passphrase, CredentialStore};
+use anyhow::{bail, Context, Result};
+use std::collections::BTreeMap;
+use std::io::{self, BufRead, IsTerminal};
+
+/// Add, remove or list keys in the encrypted credentials file
+pub fn manage_credentials(args: &CredentialsArgs) -> Result<()> {
+    let store = CredentialStore::from_env()?;
+
+    match &args.command {
+        CredentialsCommand::Set { name } => {
+            let (passphrase, mut credentials) = unlock(&store)?;
+            let key = read_key(name)?;
+            if key.is_empty() {
+                bail!("No API key given");
+            }
+
+            credentials.insert(name.clone(), key);
+            store.encrypt(&passphrase, &credentials)?;
+            println!("Stored {} in {}", name, store.path().display());
+        }
+        CredentialsCommand::Remove { name } => {
+            if !store.exists() {
+                bail!("No credentials file at {}", store.path().display());
+            }
+
+            let (passphrase, mut credentials) = unlock(&store)?;
+            if credentials.remove(name).is_none() {
+                bail!("No credential named {:?}", name);
+            }
+
+            store.encrypt(&passphrase, &credentials)?;
+            println!("Removed {}", name);
+        }
+        CredentialsCommand::List => {
+            if !store.exists() {
+                println!("No credentials stored in {}", store.path().display());
+                return Ok(());
+            }
+
+            let (_, credentials) = unlock(&store)?;
+            for name in credentials.keys() {
+                println!("{}", name);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Ask for the passphrase and decrypt the file, choosing a new passphrase if there is none yet
+fn unlock(store: &CredentialStore) -> Result<(String, BTreeMap<String, String>)> {
+    if store.exists() {
+        let passphrase = passphrase("Credentials passphrase: ")?;
+        let credentials = store.decrypt(&passphrase)?;
+        return Ok((passphrase, credentials));
+    }
+
+    let new_passphrase = passphrase("New credentials passphrase: ")?;
+    if new_passphrase.is_empty() {
+        bail!("The passphrase must not be empty");
+    }
+    if passphrase("Repeat passphrase: ")? != new_passphrase {
+        bail!("Passphrases do not match");
+    }
+    Ok((new_passphrase, BTreeMap::new()))
+}
+
+/// Read the key without echoing it, or from stdin when it is piped
+fn read_key(name: &str) -> Result<String> {
+    let key = if io::stdin().is_terminal() {
+        rpassword::prompt_password(format!("API key for {}: ", name))
+            .context("Failed to read API key")?
+    } else {
+        let mut line = String::new();
+        io::stdin()
+            .lock()
+            .read_line(&mut line)
+            .context("Failed to read API key")?;
+        line
+    };
+
+    Ok(key.trim().to_string())
+}
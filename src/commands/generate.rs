@@ -1,9 +1,24 @@
+use crate::cache::CacheMode;
 use crate::commands::cli::Cli;
-use crate::{gateway::LLMGateway, providers::ProviderType, types::ChatMessage};
+use crate::{
+    config::GatewayConfig,
+    error::GatewayError,
+    gateway::LLMGateway,
+    providers::ProviderType,
+    types::{ChatMessage, ChatRequest},
+};
 use anyhow::{Context, Result};
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::{Path, PathBuf};
 use std::process::Command;
 use std::str::FromStr;
 
+/// Completion budget used when neither the CLI nor the config sets one
+const DEFAULT_MAX_TOKENS: u32 = 8192;
+
+/// Sampling temperature used when neither the CLI nor the config sets one
+const DEFAULT_TEMPERATURE: f32 = 0.7;
+
 /// Generate a commit message using AI
 pub async fn generate_commit(cli: &Cli) -> Result<()> {
     // Get git diff
@@ -13,15 +28,34 @@ pub async fn generate_commit(cli: &Cli) -> Result<()> {
         return Ok(());
     }
 
-    // Initialize the LLM gateway
-    let gateway = LLMGateway::from_env()
-        .await
-        .context("Failed to initialize LLM gateway. Please check your environment variables.")?;
+    // Initialize the LLM gateway from config files and environment variables
+    let repo_root = get_repository_root();
+    let config = GatewayConfig::load(repo_root.as_deref(), cli.profile.as_deref()).context(
+        "Failed to load configuration. Please check your config files and environment variables.",
+    )?;
+    let generation = config.generation.clone();
+    if let Some(profile) = &config.profile {
+        println!("Using profile {}", profile);
+    }
+
+    let mut gateway = LLMGateway::new(config).context("Failed to initialize LLM gateway")?;
+    if let Some(repository) = repo_root.as_deref().and_then(Path::file_name) {
+        gateway = gateway.with_repository(repository.to_string_lossy());
+    }
+    // Running again on the same diff shows the same message until --refresh
+    let cache_mode = if cli.no_cache {
+        CacheMode::Bypass
+    } else if cli.refresh {
+        CacheMode::Refresh
+    } else {
+        CacheMode::Use
+    };
+    gateway = gateway.with_cache_mode(cache_mode).with_cache_sampled(true);
 
     // Determine which provider to use and model
     let (provider_type, _model_override) = if let Some(provider_str) = &cli.model {
         // Check if it's in provider/model format
-        let parts: Vec<&str> = provider_str.split('/').collect();
+        let parts: Vec<&str> = provider_str.splitn(2, '/').collect();
         if parts.len() == 2 {
             let provider = ProviderType::from_str(parts[0])
                 .map_err(|_| anyhow::anyhow!("Invalid provider: {}", parts[0]))?;
@@ -46,8 +80,10 @@ pub async fn generate_commit(cli: &Cli) -> Result<()> {
     }
 
     // Create the prompt for commit message generation
-    let system_prompt = create_system_prompt(cli.rules.as_deref());
-    let user_prompt = create_user_prompt(&diff, cli.context.as_deref());
+    let rules = cli.rules.as_deref().or(generation.rules.as_deref());
+    let context = cli.context.as_deref().or(generation.context.as_deref());
+    let system_prompt = create_system_prompt(rules);
+    let user_prompt = create_user_prompt(&diff, context);
 
     let messages = vec![
         ChatMessage::system(system_prompt),
@@ -56,23 +92,82 @@ pub async fn generate_commit(cli: &Cli) -> Result<()> {
 
     println!("Generating commit message using {:?}...", provider_type);
 
-    // Generate the commit message using gateway's unified method
-    let response = gateway
-        .chat_with_options(
-            messages,
-            Some(provider_type),
-            _model_override,
-            Some(cli.max_tokens),
-            Some(cli.temperature),
+    // Use the specified model or the provider's default model
+    let model = match _model_override {
+        Some(model) => model,
+        None => gateway
+            .get_provider(&provider_type)?
+            .default_model()
+            .unwrap_or("gpt-3.5-turbo")
+            .to_string(),
+    };
+
+    let mut request = ChatRequest::new(messages, model)
+        .with_max_tokens(
+            cli.max_tokens
+                .or(generation.max_tokens)
+                .unwrap_or(DEFAULT_MAX_TOKENS),
         )
+        .with_temperature(
+            cli.temperature
+                .or(generation.temperature)
+                .unwrap_or(DEFAULT_TEMPERATURE),
+        );
+    if cli.candidates > 1 {
+        request = request.with_n(cli.candidates);
+    }
+
+    // Generate the commit message using gateway's unified method
+    let response = match gateway
+        .chat_request_with_provider(request, Some(provider_type.clone()))
         .await
-        .context("Failed to generate commit message")?;
+    {
+        Ok(response) => response,
+        // Usually a misspelled or retired model name
+        Err(err @ GatewayError::NotFound(_)) => {
+            return Err(anyhow::Error::new(err).context(format!(
+                "Failed to generate commit message. Run `fuckmit models {}` to see the available models",
+                provider_type
+            )))
+        }
+        Err(err) => return Err(anyhow::Error::new(err).context("Failed to generate commit message")),
+    };
+
+    if let Some(answered_by) = &response.provider.as_ref().filter(|p| **p != provider_type) {
+        println!(
+            "{:?} was unavailable, fell back to {:?} ({})",
+            provider_type, answered_by, response.model
+        );
+    }
+
+    if response.cached {
+        println!("Using cached response (pass --refresh to regenerate)");
+    }
 
-    let commit_message = response
-        .content()
-        .ok_or_else(|| anyhow::anyhow!("No content in response"))?
-        .trim()
-        .to_string();
+    let mut candidates: Vec<String> = Vec::new();
+    for content in response.contents().map(str::trim) {
+        if !content.is_empty() && !candidates.iter().any(|c| c == content) {
+            candidates.push(content.to_string());
+        }
+    }
+    if candidates.is_empty() {
+        return Err(anyhow::anyhow!("No content in response"));
+    }
+
+    if cli.dry_run && candidates.len() > 1 {
+        println!("Generated commit messages (dry run):");
+        for (i, candidate) in candidates.iter().enumerate() {
+            println!("--- {} ---", i + 1);
+            println!("{}", candidate);
+        }
+        println!("---");
+        return Ok(());
+    }
+
+    let Some(commit_message) = choose_candidate(candidates)? else {
+        println!("Aborted, nothing committed.");
+        return Ok(());
+    };
 
     if cli.dry_run {
         println!("Generated commit message (dry run):");
@@ -89,6 +184,42 @@ pub async fn generate_commit(cli: &Cli) -> Result<()> {
     Ok(())
 }
 
+/// Let the user pick one of several candidates; `None` if they abort
+///
+/// Without a terminal to ask on, the first candidate is used.
+fn choose_candidate(mut candidates: Vec<String>) -> Result<Option<String>> {
+    if candidates.len() == 1 || !io::stdin().is_terminal() {
+        return Ok(Some(candidates.swap_remove(0)));
+    }
+
+    for (i, candidate) in candidates.iter().enumerate() {
+        println!("[{}] {}", i + 1, candidate.replace('\n', "\n    "));
+    }
+
+    loop {
+        print!(
+            "Choose a commit message [1-{}] (Enter for 1, q to abort): ",
+            candidates.len()
+        );
+        io::stdout().flush()?;
+
+        let mut answer = String::new();
+        if io::stdin().lock().read_line(&mut answer)? == 0 {
+            return Ok(None);
+        }
+        match answer.trim() {
+            "" => return Ok(Some(candidates.swap_remove(0))),
+            "q" | "Q" => return Ok(None),
+            choice => match choice.parse::<usize>() {
+                Ok(i) if (1..=candidates.len()).contains(&i) => {
+                    return Ok(Some(candidates.swap_remove(i - 1)))
+                }
+                _ => println!("Please enter a number between 1 and {}", candidates.len()),
+            },
+        }
+    }
+}
+
 /// Get the git diff for staged changes
 fn get_git_diff() -> Result<String> {
     let output = Command::new("git")
@@ -127,6 +258,21 @@ fn get_git_diff() -> Result<String> {
     Ok(diff)
 }
 
+/// Root directory of the current repository
+pub(crate) fn get_repository_root() -> Option<PathBuf> {
+    let output = Command::new("git")
+        .args(["rev-parse", "--show-toplevel"])
+        .output()
+        .ok()?;
+
+    if !output.status.success() {
+        return None;
+    }
+
+    let toplevel = String::from_utf8(output.stdout).ok()?;
+    Some(PathBuf::from(toplevel.trim()))
+}
+
 /// Create the system prompt for commit message generation
 fn create_system_prompt(additional_rules: Option<&str>) -> String {
     let mut prompt = r#"You are an expert at writing clear, concise git commit messages following conventional commit format.
@@ -1,2 +1,6 @@
 pub mod cli;
+pub mod credentials;
 pub mod generate;
+pub mod models;
+pub mod serve;
+pub mod usage;
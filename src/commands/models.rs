@@ -0,0 +1,73 @@
+use crate::commands::cli::{Cli, ModelsArgs};
+use crate::commands::generate::get_repository_root;
+use crate::config::GatewayConfig;
+use crate::gateway::LLMGateway;
+use crate::providers::ProviderType;
+use anyhow::{Context, Result};
+
+/// List the models offered by the configured providers
+pub async fn list_models(cli: &Cli, args: &ModelsArgs) -> Result<()> {
+    let repo_root = get_repository_root();
+    let config = GatewayConfig::load(repo_root.as_deref(), cli.profile.as_deref()).context(
+        "Failed to load configuration. Please check your config files and environment variables.",
+    )?;
+    let gateway = LLMGateway::new(config).context("Failed to initialize LLM gateway")?;
+
+    let results = match &args.provider {
+        Some(name) => {
+            let provider_type: ProviderType = name.parse().map_err(anyhow::Error::msg)?;
+            if !gateway.has_provider(&provider_type) {
+                return Err(anyhow::anyhow!(
+                    "Provider {} is not configured",
+                    provider_type
+                ));
+            }
+            let models = gateway.list_models_with_provider(&provider_type).await;
+            vec![(provider_type, models)]
+        }
+        None => gateway.list_models().await,
+    };
+
+    let default_provider = gateway.default_provider();
+    let mut failures = 0;
+    for (provider_type, models) in &results {
+        let provider = gateway.get_provider(provider_type)?;
+        let default_model = provider.default_model();
+
+        if *provider_type == default_provider {
+            println!("{} (default provider)", provider_type);
+        } else {
+            println!("{}", provider_type);
+        }
+
+        match models {
+            Ok(models) if models.is_empty() => println!("  No models listed"),
+            Ok(models) => {
+                for model in models {
+                    let mut line = format!("  {}", model.id);
+                    if let Some(underlying) = model.model.as_deref().filter(|m| *m != model.id) {
+                        line.push_str(&format!(" ({})", underlying));
+                    }
+                    if Some(model.id.as_str()) == default_model {
+                        line.push_str(" [default]");
+                    }
+                    println!("{}", line);
+                }
+            }
+            Err(err) => {
+                failures += 1;
+                println!("  Failed to list models: {}", err);
+            }
+        }
+
+        // Listing every deployment needs the Azure management API rather than an API key
+        if *provider_type == ProviderType::Azure {
+            println!("  Only the configured deployment is listed; other deployments can be used by name");
+        }
+    }
+
+    if failures > 0 && failures == results.len() {
+        return Err(anyhow::anyhow!("No provider could list its models"));
+    }
+    Ok(())
+}
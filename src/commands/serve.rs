@@ -0,0 +1,198 @@
+use crate::cache::CacheMode;
+use crate::commands::cli::{Cli, ServeArgs};
+use crate::commands::generate::get_repository_root;
+use crate::config::GatewayConfig;
+use crate::error::GatewayError;
+use crate::gateway::LLMGateway;
+use crate::providers::ProviderType;
+use crate::types::{ChatRequest, ModelInfo};
+use anyhow::{Context, Result};
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::sse::{Event, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use futures::{stream, StreamExt};
+use serde_json::json;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+/// Serve an OpenAI-compatible API backed by the gateway
+pub async fn serve(cli: &Cli, args: &ServeArgs) -> Result<()> {
+    let repo_root = get_repository_root();
+    let config = GatewayConfig::load(repo_root.as_deref(), cli.profile.as_deref()).context(
+        "Failed to load configuration. Please check your config files and environment variables.",
+    )?;
+    let mut gateway = LLMGateway::new(config).context("Failed to initialize LLM gateway")?;
+    if args.cache {
+        gateway = gateway.with_cache_mode(CacheMode::Use);
+    }
+
+    let app = Router::new()
+        .route("/v1/chat/completions", post(chat_completions))
+        .route("/v1/models", get(models))
+        .with_state(Arc::new(gateway));
+
+    let addr = SocketAddr::new(args.host, args.port);
+    let listener = tokio::net::TcpListener::bind(addr)
+        .await
+        .with_context(|| format!("Failed to listen on {}", addr))?;
+    println!("Serving OpenAI-compatible API on http://{}/v1", addr);
+
+    axum::serve(listener, app)
+        .with_graceful_shutdown(async {
+            let _ = tokio::signal::ctrl_c().await;
+        })
+        .await
+        .context("Server failed")
+}
+
+/// `POST /v1/chat/completions`, streamed as server-sent events when `stream` is set
+async fn chat_completions(
+    State(gateway): State<Arc<LLMGateway>>,
+    Json(mut request): Json<ChatRequest>,
+) -> Response {
+    let provider_type = match route(&gateway, &mut request) {
+        Ok(provider_type) => provider_type,
+        Err(err) => return error_response(&err),
+    };
+
+    if request.stream.take().unwrap_or(false) {
+        let chunks = match gateway
+            .chat_request_stream_with_provider(request, Some(provider_type))
+            .await
+        {
+            Ok(chunks) => chunks,
+            Err(err) => return error_response(&err),
+        };
+
+        let events = chunks
+            .map(|chunk| {
+                let data = match chunk {
+                    Ok(chunk) => serde_json::to_string(&chunk).unwrap_or_default(),
+                    Err(err) => error_body(&err).to_string(),
+                };
+                Ok::<_, Infallible>(Event::default().data(data))
+            })
+            .chain(stream::once(async { Ok(Event::default().data("[DONE]")) }));
+        return Sse::new(events).into_response();
+    }
+
+    match gateway
+        .chat_request_with_provider(request, Some(provider_type))
+        .await
+    {
+        Ok(response) => Json(response).into_response(),
+        Err(err) => error_response(&err),
+    }
+}
+
+/// `GET /v1/models`: the models every configured provider lists
+///
+/// A provider that cannot list its models is represented by its default model.
+async fn models(State(gateway): State<Arc<LLMGateway>>) -> Response {
+    let mut data = Vec::new();
+    for (provider_type, models) in gateway.list_models().await {
+        let models = match models {
+            Ok(models) => models,
+            Err(_) => gateway
+                .get_provider(&provider_type)
+                .ok()
+                .and_then(|provider| provider.default_model().map(ModelInfo::new))
+                .into_iter()
+                .collect(),
+        };
+
+        data.extend(models.into_iter().map(|model| {
+            json!({
+                "id": format!("{}/{}", provider_type, model.id),
+                "object": "model",
+                "created": model.created.unwrap_or_default(),
+                "owned_by": model.owned_by.unwrap_or_else(|| provider_type.to_string()),
+            })
+        }));
+    }
+
+    Json(json!({ "object": "list", "data": data })).into_response()
+}
+
+/// Pick the provider for a request from its model, the way `LLM_MODEL` does
+///
+/// `provider/model` selects a configured provider and strips the prefix; a
+/// bare provider name uses that provider's default model. Anything else,
+/// including model names with a slash such as `meta-llama/llama-3`, goes to
+/// the default provider unchanged.
+fn route(gateway: &LLMGateway, request: &mut ChatRequest) -> crate::Result<ProviderType> {
+    let configured = |name: &str| {
+        name.parse::<ProviderType>()
+            .ok()
+            .filter(|provider_type| gateway.has_provider(provider_type))
+    };
+
+    let provider_type = if let Some((provider_type, model)) = request
+        .model
+        .split_once('/')
+        .and_then(|(provider, model)| Some((configured(provider)?, model.to_string())))
+    {
+        request.model = model;
+        provider_type
+    } else if let Some(provider_type) = configured(&request.model) {
+        request.model.clear();
+        provider_type
+    } else {
+        gateway.default_provider()
+    };
+
+    if request.model.is_empty() {
+        request.model = gateway
+            .get_provider(&provider_type)?
+            .default_model()
+            .ok_or_else(|| {
+                GatewayError::InvalidRequest(format!("No model given for {}", provider_type))
+            })?
+            .to_string();
+    }
+    Ok(provider_type)
+}
+
+/// HTTP status a gateway error is reported with
+///
+/// Upstream auth failures become 502 so that clients don't mistake them for
+/// a problem with their own credentials.
+fn status(err: &GatewayError) -> StatusCode {
+    match err {
+        GatewayError::InvalidRequest(_)
+        | GatewayError::BadRequest(_)
+        | GatewayError::ContentFiltered(_)
+        | GatewayError::ContextLengthExceeded(_) => StatusCode::BAD_REQUEST,
+        GatewayError::NotFound(_) => StatusCode::NOT_FOUND,
+        GatewayError::RateLimited(_) | GatewayError::RateLimit(_) => StatusCode::TOO_MANY_REQUESTS,
+        GatewayError::Timeout(_) => StatusCode::GATEWAY_TIMEOUT,
+        GatewayError::Config(_) | GatewayError::Auth(_) | GatewayError::Io(_) => {
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+        _ => StatusCode::BAD_GATEWAY,
+    }
+}
+
+/// OpenAI-style error body
+fn error_body(err: &GatewayError) -> serde_json::Value {
+    let error_type = if status(err).is_client_error() {
+        "invalid_request_error"
+    } else {
+        "api_error"
+    };
+    json!({
+        "error": {
+            "message": err.to_string(),
+            "type": error_type,
+            "code": err.api_error().and_then(|api_error| api_error.code.clone()),
+        }
+    })
+}
+
+fn error_response(err: &GatewayError) -> Response {
+    (status(err), Json(error_body(err))).into_response()
+}
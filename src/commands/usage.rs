@@ -0,0 +1,109 @@
+use crate::commands::cli::{Cli, UsageArgs};
+use crate::commands::generate::get_repository_root;
+use crate::config::GatewayConfig;
+use crate::usage::{totals_by, PriceTable, UsageLedger, UsageRecord, UsageTotals};
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Report usage and cost recorded in the ledger
+pub fn show_usage(cli: &Cli, args: &UsageArgs) -> Result<()> {
+    let repo_root = get_repository_root();
+    let config = GatewayConfig::load_unvalidated(repo_root.as_deref(), cli.profile.as_deref())
+        .context(
+        "Failed to load configuration. Please check your config files and environment variables.",
+    )?;
+    let Some(path) = config.usage_ledger else {
+        println!("Usage recording is disabled.");
+        return Ok(());
+    };
+
+    let prices = match args.prices.clone().or_else(PriceTable::default_path) {
+        Some(path) => PriceTable::load(&path).context("Failed to load price table")?,
+        None => PriceTable::default(),
+    };
+
+    let mut records = UsageLedger::new(&path)
+        .records()
+        .context("Failed to read usage ledger")?;
+
+    if args.days > 0 {
+        let since = SystemTime::now()
+            .checked_sub(Duration::from_secs(u64::from(args.days) * 24 * 60 * 60))
+            .and_then(|since| since.duration_since(UNIX_EPOCH).ok())
+            .map(|since| since.as_secs())
+            .unwrap_or_default();
+        records.retain(|record| record.timestamp >= since);
+    }
+
+    if records.is_empty() {
+        println!("No usage recorded in {}", path.display());
+        return Ok(());
+    }
+
+    let period = if args.days > 0 {
+        format!("last {} days", args.days)
+    } else {
+        "all time".to_string()
+    };
+    println!("Usage for {} ({})", period, path.display());
+
+    print_totals("Day", &totals_by(&records, &prices, UsageRecord::day));
+    print_totals(
+        "Model",
+        &totals_by(&records, &prices, |record| {
+            format!("{}/{}", record.provider, record.model)
+        }),
+    );
+    print_totals(
+        "Repository",
+        &totals_by(&records, &prices, |record| {
+            record.repository.clone().unwrap_or_else(|| "-".to_string())
+        }),
+    );
+
+    let mut total = UsageTotals::default();
+    for record in &records {
+        total.add(record, &prices);
+    }
+    println!();
+    println!(
+        "Total: {} calls, {} prompt + {} completion tokens, ${:.4}",
+        total.calls, total.prompt_tokens, total.completion_tokens, total.cost
+    );
+    if total.unpriced_calls > 0 {
+        println!(
+            "{} calls used models without a price; add them to the price table to include their cost.",
+            total.unpriced_calls
+        );
+    }
+
+    Ok(())
+}
+
+/// Print one table of totals
+fn print_totals(title: &str, totals: &BTreeMap<String, UsageTotals>) {
+    let width = totals
+        .keys()
+        .map(|key| key.len())
+        .chain([title.len()])
+        .max()
+        .unwrap_or_default();
+
+    println!();
+    println!(
+        "{:<width$}  {:>7}  {:>12}  {:>12}  {:>9}  {:>10}",
+        title, "Calls", "Prompt", "Completion", "Latency", "Cost"
+    );
+    for (key, totals) in totals {
+        println!(
+            "{:<width$}  {:>7}  {:>12}  {:>12}  {:>8.1}s  {:>10}",
+            key,
+            totals.calls,
+            totals.prompt_tokens,
+            totals.completion_tokens,
+            totals.average_latency().as_secs_f64(),
+            format!("${:.4}", totals.cost)
+        );
+    }
+}
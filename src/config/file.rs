@@ -0,0 +1,303 @@
+//! Configuration files (`config.toml` / `config.yaml`)
+//!
+//! Every setting in a file is optional so that the user file and the
+//! repository file can be merged key by key before being applied.
+
+use super::{
+    optional_path, parse_providers, AuthStyle, GatewayConfig, GenerationConfig, NetworkConfig,
+    ProviderConfig,
+};
+use crate::credentials::{ApiKey, KeySource};
+use crate::error::{GatewayError, Result};
+use crate::providers::ProviderType;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Supported file extensions, in lookup order
+const EXTENSIONS: &[&str] = &["toml", "yaml", "yml"];
+
+/// Existing config files named `name` in `dir`
+pub(super) fn candidates(dir: &Path, name: &str) -> Vec<PathBuf> {
+    EXTENSIONS
+        .iter()
+        .map(|ext| dir.join(format!("{}.{}", name, ext)))
+        .filter(|path| path.is_file())
+        .collect()
+}
+
+/// Whether network settings could route requests through someone else's
+/// proxy, trust their certificates or present the user's client certificate
+fn intercepts(network: &NetworkConfig) -> bool {
+    network.proxy.is_some()
+        || !network.ca_certs.is_empty()
+        || network.client_cert.is_some()
+        || network.client_key.is_some()
+}
+
+/// Settings read from config files
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub(super) struct FileConfig {
+    /// Default provider and model in `provider/model` format
+    model: Option<String>,
+    providers: HashMap<String, FileProviderConfig>,
+    fallback_providers: Option<Vec<String>>,
+    timeout_seconds: Option<u64>,
+    connect_timeout_seconds: Option<u64>,
+    read_timeout_seconds: Option<u64>,
+    max_retries: Option<u32>,
+    headers: HashMap<String, String>,
+    network: NetworkConfig,
+    usage_ledger: Option<String>,
+    cache_dir: Option<String>,
+    cache_ttl_seconds: Option<u64>,
+    cache_max_mb: Option<u64>,
+    generation: GenerationConfig,
+    profiles: HashMap<String, Profile>,
+}
+
+/// Named bundle of model and generation settings, selected with `--profile`
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub(super) struct Profile {
+    /// Provider and model in `provider/model` format, or just a provider
+    model: Option<String>,
+    max_tokens: Option<u32>,
+    temperature: Option<f32>,
+    rules: Option<String>,
+    context: Option<String>,
+}
+
+/// Provider settings read from config files
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct FileProviderConfig {
+    api_key: Option<String>,
+    api_key_cmd: Option<String>,
+    api_key_file: Option<PathBuf>,
+    api_key_credential: Option<String>,
+    base_url: Option<String>,
+    #[serde(alias = "model")]
+    default_model: Option<String>,
+    timeout_seconds: Option<u64>,
+    connect_timeout_seconds: Option<u64>,
+    read_timeout_seconds: Option<u64>,
+    auth: Option<String>,
+    headers: HashMap<String, String>,
+    network: NetworkConfig,
+    extra: HashMap<String, String>,
+}
+
+impl FileConfig {
+    /// Read and merge `paths`, later files overriding earlier ones key by key
+    pub(super) fn load(paths: &[PathBuf]) -> Result<Self> {
+        let mut builder = ::config::Config::builder();
+        for path in paths {
+            builder = builder.add_source(::config::File::from(path.as_path()));
+        }
+
+        builder
+            .build()
+            .and_then(|merged| merged.try_deserialize())
+            .map_err(|e| GatewayError::Config(format!("Invalid configuration file: {}", e)))
+    }
+
+    /// Reject settings a repository file must not use
+    ///
+    /// Key commands, key files and stored credentials are only read from the
+    /// user config file, so that a cloned repository cannot run commands or
+    /// read files on the user's machine. Proxies, certificates and extra
+    /// headers would let it intercept requests and their API keys, and file
+    /// locations would let it choose where fuckmit writes.
+    pub(super) fn check_repository_file(&self) -> Result<()> {
+        let denied = |key: &str| {
+            Err(GatewayError::Config(format!(
+                "{} is only allowed in the user config file, not in the repository's .fuckmit file",
+                key
+            )))
+        };
+
+        if !self.headers.is_empty() {
+            return denied("headers");
+        }
+        if intercepts(&self.network) {
+            return denied("network");
+        }
+        if self.usage_ledger.is_some() {
+            return denied("usage_ledger");
+        }
+        if self.cache_dir.is_some() {
+            return denied("cache_dir");
+        }
+
+        for (name, provider) in &self.providers {
+            if provider.api_key_cmd.is_some()
+                || provider.api_key_file.is_some()
+                || provider.api_key_credential.is_some()
+            {
+                return Err(GatewayError::Config(format!(
+                    "providers.{}: api_key_cmd, api_key_file and api_key_credential are only \
+                     allowed in the user config file",
+                    name
+                )));
+            }
+            if !provider.headers.is_empty() {
+                return denied(&format!("providers.{}.headers", name));
+            }
+            if intercepts(&provider.network) {
+                return denied(&format!("providers.{}.network", name));
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether the file sets the base URL of a provider
+    pub(super) fn sets_base_url(&self, provider_type: &ProviderType) -> bool {
+        self.provider(provider_type)
+            .is_some_and(|provider| provider.base_url.is_some())
+    }
+
+    /// Whether the file sets the API key of a provider
+    pub(super) fn sets_api_key(&self, provider_type: &ProviderType) -> bool {
+        self.provider(provider_type).is_some_and(|provider| {
+            provider.api_key.is_some()
+                || provider.api_key_cmd.is_some()
+                || provider.api_key_file.is_some()
+                || provider.api_key_credential.is_some()
+        })
+    }
+
+    fn provider(&self, provider_type: &ProviderType) -> Option<&FileProviderConfig> {
+        self.providers
+            .iter()
+            .find(|(name, _)| ProviderType::from_str(name).ok().as_ref() == Some(provider_type))
+            .map(|(_, provider)| provider)
+    }
+
+    /// Remove and return the profile called `name`
+    pub(super) fn take_profile(&mut self, name: &str) -> Result<Profile> {
+        self.profiles.remove(name).ok_or_else(|| {
+            let mut available: Vec<_> = self.profiles.keys().map(String::as_str).collect();
+            available.sort_unstable();
+            GatewayError::Config(format!(
+                "Unknown profile {:?}. Available profiles: {}",
+                name,
+                if available.is_empty() {
+                    "none".to_string()
+                } else {
+                    available.join(", ")
+                }
+            ))
+        })
+    }
+
+    /// Apply the file settings on top of `config`
+    pub(super) fn apply(self, config: &mut GatewayConfig) -> Result<()> {
+        if let Some(model) = &self.model {
+            config.set_model(model, "config file model")?;
+        }
+
+        for (name, file_provider) in self.providers {
+            let provider_type = ProviderType::from_str(&name).map_err(|_| {
+                GatewayError::Config(format!("Invalid provider in config file: {}", name))
+            })?;
+            let provider_config = config
+                .providers
+                .entry(provider_type)
+                .or_insert_with(|| ProviderConfig::new(""));
+            file_provider.apply(provider_config)?;
+        }
+
+        if let Some(fallbacks) = self.fallback_providers {
+            config.fallback_providers =
+                parse_providers(&fallbacks.join(","), "config file fallback_providers")?;
+        }
+
+        config.timeout_seconds = self.timeout_seconds.or(config.timeout_seconds);
+        config.connect_timeout_seconds = self
+            .connect_timeout_seconds
+            .or(config.connect_timeout_seconds);
+        config.read_timeout_seconds = self.read_timeout_seconds.or(config.read_timeout_seconds);
+        config.max_retries = self.max_retries.or(config.max_retries);
+        config.headers.extend(self.headers);
+        config.network = self.network.or(&config.network);
+
+        if let Some(path) = self.usage_ledger {
+            config.usage_ledger = optional_path(path);
+        }
+        if let Some(dir) = self.cache_dir {
+            config.cache_dir = optional_path(dir);
+        }
+        config.cache_ttl_seconds = self.cache_ttl_seconds.or(config.cache_ttl_seconds);
+        if let Some(megabytes) = self.cache_max_mb {
+            config.cache_max_bytes = Some(megabytes * 1024 * 1024);
+        }
+
+        let generation = &mut config.generation;
+        generation.max_tokens = self.generation.max_tokens.or(generation.max_tokens);
+        generation.temperature = self.generation.temperature.or(generation.temperature);
+        generation.rules = self.generation.rules.or(generation.rules.take());
+        generation.context = self.generation.context.or(generation.context.take());
+
+        Ok(())
+    }
+}
+
+impl Profile {
+    /// Apply the profile on top of `config`
+    pub(super) fn apply(self, config: &mut GatewayConfig) -> Result<()> {
+        match self.model.as_deref() {
+            Some(model) if model.contains('/') => config.set_model(model, "profile model")?,
+            Some(provider) => {
+                let provider_type = ProviderType::from_str(provider).map_err(|_| {
+                    GatewayError::Config(format!("Invalid provider in profile: {}", provider))
+                })?;
+                // A model selected for another provider does not carry over
+                if provider_type != config.default_provider {
+                    config.default_provider = provider_type;
+                    config.default_model = None;
+                }
+            }
+            None => {}
+        }
+
+        let generation = &mut config.generation;
+        generation.max_tokens = self.max_tokens.or(generation.max_tokens);
+        generation.temperature = self.temperature.or(generation.temperature);
+        generation.rules = self.rules.or(generation.rules.take());
+        generation.context = self.context.or(generation.context.take());
+        Ok(())
+    }
+}
+
+impl FileProviderConfig {
+    fn apply(self, provider_config: &mut ProviderConfig) -> Result<()> {
+        if let Some(api_key) = self.api_key {
+            provider_config.api_key = ApiKey::new(api_key);
+        } else if let Some(command) = self.api_key_cmd {
+            provider_config.api_key = ApiKey::from_source(KeySource::Command(command));
+        } else if let Some(path) = self.api_key_file {
+            provider_config.api_key = ApiKey::from_source(KeySource::File(path));
+        } else if let Some(name) = self.api_key_credential {
+            provider_config.api_key = ApiKey::from_source(KeySource::Credential(name));
+        }
+        provider_config.base_url = self.base_url.or(provider_config.base_url.take());
+        provider_config.default_model = self.default_model.or(provider_config.default_model.take());
+        provider_config.timeout_seconds = self.timeout_seconds.or(provider_config.timeout_seconds);
+        provider_config.connect_timeout_seconds = self
+            .connect_timeout_seconds
+            .or(provider_config.connect_timeout_seconds);
+        provider_config.read_timeout_seconds = self
+            .read_timeout_seconds
+            .or(provider_config.read_timeout_seconds);
+        if let Some(auth) = self.auth {
+            provider_config.auth = AuthStyle::from_str(&auth).map_err(GatewayError::Config)?;
+        }
+        provider_config.headers.extend(self.headers);
+        provider_config.network = self.network.or(&provider_config.network);
+        provider_config.extra.extend(self.extra);
+        Ok(())
+    }
+}
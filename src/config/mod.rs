@@ -0,0 +1,1124 @@
+//! Configuration management for the LLM Gateway
+
+mod file;
+
+use crate::cache::ResponseCache;
+use crate::cassette::{CassetteConfig, CassetteMode};
+use crate::credentials::{ApiKey, KeySource};
+use crate::error::{GatewayError, Result};
+use crate::providers::ProviderType;
+use crate::usage::UsageLedger;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// How the API key is sent to an OpenAI-compatible provider
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuthStyle {
+    /// `Authorization: Bearer <key>`
+    #[default]
+    Bearer,
+    /// `api-key: <key>`, as used by Azure-style gateways
+    ApiKey,
+    /// The raw key in a custom header
+    Header(String),
+    /// No authentication
+    None,
+}
+
+impl FromStr for AuthStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "bearer" => Ok(AuthStyle::Bearer),
+            "api_key" | "api-key" => Ok(AuthStyle::ApiKey),
+            "none" => Ok(AuthStyle::None),
+            _ => match s.strip_prefix("header:") {
+                Some(header) if !header.is_empty() => Ok(AuthStyle::Header(header.to_string())),
+                _ => Err(format!("Unknown auth style: {}", s)),
+            },
+        }
+    }
+}
+
+/// Configuration for a specific provider
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProviderConfig {
+    /// API key for the provider, resolved when first used
+    #[serde(default)]
+    pub api_key: ApiKey,
+    /// Base URL for the provider's API
+    pub base_url: Option<String>,
+    /// Default model to use
+    pub default_model: Option<String>,
+    /// Total request timeout in seconds, overriding the gateway setting
+    pub timeout_seconds: Option<u64>,
+    /// Connect timeout in seconds, overriding the gateway setting
+    pub connect_timeout_seconds: Option<u64>,
+    /// Idle read timeout in seconds, overriding the gateway setting
+    pub read_timeout_seconds: Option<u64>,
+    /// How the API key is sent (custom providers only)
+    #[serde(default)]
+    pub auth: AuthStyle,
+    /// Extra HTTP headers sent with every request
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    /// Proxy and TLS settings, overriding the gateway settings
+    #[serde(default)]
+    pub network: NetworkConfig,
+    /// Cassette HTTP traffic is recorded to or replayed from
+    #[serde(default)]
+    pub cassette: Option<CassetteConfig>,
+    /// Additional provider-specific configuration
+    pub extra: HashMap<String, String>,
+}
+
+impl ProviderConfig {
+    /// Create a new provider configuration
+    pub fn new(api_key: impl Into<ApiKey>) -> Self {
+        Self {
+            api_key: api_key.into(),
+            base_url: None,
+            default_model: None,
+            timeout_seconds: None,
+            connect_timeout_seconds: None,
+            read_timeout_seconds: None,
+            auth: AuthStyle::default(),
+            headers: HashMap::new(),
+            network: NetworkConfig::default(),
+            cassette: None,
+            extra: HashMap::new(),
+        }
+    }
+
+    /// Read the API key from a command, file or the credentials file instead
+    pub fn with_api_key_source(mut self, source: KeySource) -> Self {
+        self.api_key = ApiKey::from_source(source);
+        self
+    }
+
+    /// Set the base URL
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = Some(base_url.into());
+        self
+    }
+
+    /// Set the default model
+    pub fn with_default_model(mut self, model: impl Into<String>) -> Self {
+        self.default_model = Some(model.into());
+        self
+    }
+
+    /// Set the total request timeout
+    pub fn with_timeout(mut self, timeout_seconds: u64) -> Self {
+        self.timeout_seconds = Some(timeout_seconds);
+        self
+    }
+
+    /// Set the connect timeout
+    pub fn with_connect_timeout(mut self, timeout_seconds: u64) -> Self {
+        self.connect_timeout_seconds = Some(timeout_seconds);
+        self
+    }
+
+    /// Set the idle read timeout
+    pub fn with_read_timeout(mut self, timeout_seconds: u64) -> Self {
+        self.read_timeout_seconds = Some(timeout_seconds);
+        self
+    }
+
+    /// Set how the API key is sent
+    pub fn with_auth(mut self, auth: AuthStyle) -> Self {
+        self.auth = auth;
+        self
+    }
+
+    /// Add an extra HTTP header
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.insert(name.into(), value.into());
+        self
+    }
+
+    /// Set the proxy and TLS settings
+    pub fn with_network(mut self, network: NetworkConfig) -> Self {
+        self.network = network;
+        self
+    }
+
+    /// Record HTTP traffic to, or replay it from, a cassette
+    pub fn with_cassette(mut self, cassette: CassetteConfig) -> Self {
+        self.cassette = Some(cassette);
+        self
+    }
+
+    /// Add extra configuration
+    pub fn with_extra(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.extra.insert(key.into(), value.into());
+        self
+    }
+
+    /// Fill unset settings from the gateway-wide configuration
+    pub fn inherit(mut self, gateway: &GatewayConfig) -> Self {
+        self.timeout_seconds = self.timeout_seconds.or(gateway.timeout_seconds);
+        self.connect_timeout_seconds = self
+            .connect_timeout_seconds
+            .or(gateway.connect_timeout_seconds);
+        self.read_timeout_seconds = self.read_timeout_seconds.or(gateway.read_timeout_seconds);
+        for (name, value) in &gateway.headers {
+            self.headers
+                .entry(name.clone())
+                .or_insert_with(|| value.clone());
+        }
+        self.network = self.network.or(&gateway.network);
+        self.cassette = self.cassette.or_else(|| gateway.cassette.clone());
+        self
+    }
+}
+
+/// Proxy, TLS and User-Agent settings of the HTTP client
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NetworkConfig {
+    /// Proxy URL (`http://`, `https://`, `socks5://` or `socks5h://`, optionally with `user:password@`)
+    pub proxy: Option<String>,
+    /// Comma-separated hosts, domains and IP ranges that bypass the proxy
+    pub no_proxy: Option<String>,
+    /// Extra root certificates (PEM) to trust
+    pub ca_certs: Vec<PathBuf>,
+    /// Client certificate (PEM) for mutual TLS
+    pub client_cert: Option<PathBuf>,
+    /// Client private key (PKCS#8 PEM) for mutual TLS
+    pub client_key: Option<PathBuf>,
+    /// User-Agent header, instead of `fuckmit/<version>`
+    pub user_agent: Option<String>,
+}
+
+impl NetworkConfig {
+    /// Send requests through a proxy
+    pub fn with_proxy(mut self, proxy: impl Into<String>) -> Self {
+        self.proxy = Some(proxy.into());
+        self
+    }
+
+    /// Set the hosts that bypass the proxy
+    pub fn with_no_proxy(mut self, no_proxy: impl Into<String>) -> Self {
+        self.no_proxy = Some(no_proxy.into());
+        self
+    }
+
+    /// Trust the root certificates in a PEM file
+    pub fn with_ca_cert(mut self, path: impl Into<PathBuf>) -> Self {
+        self.ca_certs.push(path.into());
+        self
+    }
+
+    /// Authenticate with a client certificate and key
+    pub fn with_client_identity(
+        mut self,
+        cert: impl Into<PathBuf>,
+        key: impl Into<PathBuf>,
+    ) -> Self {
+        self.client_cert = Some(cert.into());
+        self.client_key = Some(key.into());
+        self
+    }
+
+    /// Set the User-Agent header
+    pub fn with_user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = Some(user_agent.into());
+        self
+    }
+
+    /// Fill unset settings from `fallback`; root certificates from both are trusted
+    pub fn or(mut self, fallback: &NetworkConfig) -> Self {
+        self.proxy = self.proxy.or_else(|| fallback.proxy.clone());
+        self.no_proxy = self.no_proxy.or_else(|| fallback.no_proxy.clone());
+        for path in &fallback.ca_certs {
+            if !self.ca_certs.contains(path) {
+                self.ca_certs.push(path.clone());
+            }
+        }
+        // The certificate and key only make sense as a pair
+        if self.client_cert.is_none() && self.client_key.is_none() {
+            self.client_cert = fallback.client_cert.clone();
+            self.client_key = fallback.client_key.clone();
+        }
+        self.user_agent = self.user_agent.or_else(|| fallback.user_agent.clone());
+        self
+    }
+}
+
+/// Main gateway configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GatewayConfig {
+    /// Default provider to use
+    pub default_provider: ProviderType,
+    /// Model for the default provider, overriding the provider's own default
+    #[serde(default)]
+    pub default_model: Option<String>,
+    /// Configuration for each provider
+    pub providers: HashMap<ProviderType, ProviderConfig>,
+    /// Providers to try, in order, when the selected one fails
+    #[serde(default)]
+    pub fallback_providers: Vec<ProviderType>,
+    /// Global timeout in seconds
+    pub timeout_seconds: Option<u64>,
+    /// Global connect timeout in seconds
+    pub connect_timeout_seconds: Option<u64>,
+    /// Global idle read timeout in seconds
+    pub read_timeout_seconds: Option<u64>,
+    /// Maximum number of retries
+    pub max_retries: Option<u32>,
+    /// Extra HTTP headers sent to every provider
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    /// Proxy and TLS settings for every provider
+    #[serde(default)]
+    pub network: NetworkConfig,
+    /// Cassette HTTP traffic of every provider is recorded to or replayed from
+    #[serde(default)]
+    pub cassette: Option<CassetteConfig>,
+    /// Ledger file that completed calls are recorded in
+    #[serde(default)]
+    pub usage_ledger: Option<PathBuf>,
+    /// Directory responses are cached in
+    #[serde(default)]
+    pub cache_dir: Option<PathBuf>,
+    /// How long cached responses stay valid, in seconds
+    #[serde(default)]
+    pub cache_ttl_seconds: Option<u64>,
+    /// Size cap of the response cache, in bytes
+    #[serde(default)]
+    pub cache_max_bytes: Option<u64>,
+    /// Defaults for generated commit messages
+    #[serde(default)]
+    pub generation: GenerationConfig,
+    /// Name of the profile the configuration was loaded with
+    #[serde(default)]
+    pub profile: Option<String>,
+}
+
+/// Defaults for generated commit messages
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GenerationConfig {
+    /// Maximum number of tokens for the generated message
+    pub max_tokens: Option<u32>,
+    /// Sampling temperature
+    pub temperature: Option<f32>,
+    /// Additional rules for commit message generation
+    pub rules: Option<String>,
+    /// Additional context for the changes
+    pub context: Option<String>,
+}
+
+impl GatewayConfig {
+    /// Create a new gateway configuration
+    pub fn new(default_provider: ProviderType) -> Self {
+        Self {
+            default_provider,
+            default_model: None,
+            providers: HashMap::new(),
+            fallback_providers: Vec::new(),
+            timeout_seconds: None,
+            connect_timeout_seconds: None,
+            read_timeout_seconds: None,
+            max_retries: None,
+            headers: HashMap::new(),
+            network: NetworkConfig::default(),
+            cassette: None,
+            usage_ledger: None,
+            cache_dir: None,
+            cache_ttl_seconds: None,
+            cache_max_bytes: None,
+            generation: GenerationConfig::default(),
+            profile: None,
+        }
+    }
+
+    /// Add a provider configuration
+    pub fn with_provider(mut self, provider_type: ProviderType, config: ProviderConfig) -> Self {
+        self.providers.insert(provider_type, config);
+        self
+    }
+
+    /// Append a provider to the fallback chain
+    pub fn with_fallback(mut self, provider_type: ProviderType) -> Self {
+        self.fallback_providers.push(provider_type);
+        self
+    }
+
+    /// Set global timeout
+    pub fn with_timeout(mut self, timeout_seconds: u64) -> Self {
+        self.timeout_seconds = Some(timeout_seconds);
+        self
+    }
+
+    /// Set global connect timeout
+    pub fn with_connect_timeout(mut self, timeout_seconds: u64) -> Self {
+        self.connect_timeout_seconds = Some(timeout_seconds);
+        self
+    }
+
+    /// Set global idle read timeout
+    pub fn with_read_timeout(mut self, timeout_seconds: u64) -> Self {
+        self.read_timeout_seconds = Some(timeout_seconds);
+        self
+    }
+
+    /// Set maximum retries
+    pub fn with_max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = Some(max_retries);
+        self
+    }
+
+    /// Add an extra HTTP header sent to every provider
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.insert(name.into(), value.into());
+        self
+    }
+
+    /// Set the proxy and TLS settings for every provider
+    pub fn with_network(mut self, network: NetworkConfig) -> Self {
+        self.network = network;
+        self
+    }
+
+    /// Record HTTP traffic of every provider to, or replay it from, a cassette
+    pub fn with_cassette(mut self, cassette: CassetteConfig) -> Self {
+        self.cassette = Some(cassette);
+        self
+    }
+
+    /// Record completed calls in the ledger at `path`
+    pub fn with_usage_ledger(mut self, path: impl Into<PathBuf>) -> Self {
+        self.usage_ledger = Some(path.into());
+        self
+    }
+
+    /// Cache responses in `dir`
+    pub fn with_cache_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.cache_dir = Some(dir.into());
+        self
+    }
+
+    /// Load configuration from environment variables
+    pub fn from_env() -> Result<Self> {
+        let mut config = Self::with_builtin_defaults();
+        config.apply_env(&HashSet::new())?;
+        config.finalize()?;
+        Ok(config)
+    }
+
+    /// Load configuration from config files and environment variables
+    ///
+    /// Settings are layered, later layers overriding earlier ones: built-in
+    /// defaults, the user config file, the repository config file, then the
+    /// environment (including a `.env` file in the repository root), then
+    /// the selected profile (`profile`, or `FUCKMIT_PROFILE` when `None`).
+    ///
+    /// The repository config file and `.env` file come with the repository,
+    /// so they may not use key commands, key files or stored credentials, set
+    /// proxies, certificates, headers or file locations, nor change the base
+    /// URL of a provider whose key is configured elsewhere.
+    pub fn load(repo_root: Option<&Path>, profile: Option<&str>) -> Result<Self> {
+        let mut config = Self::load_unvalidated(repo_root, profile)?;
+        config.finalize()?;
+        Ok(config)
+    }
+
+    /// Load the configuration like [`load`](Self::load), without requiring
+    /// a configured provider
+    ///
+    /// For commands that only read settings, such as the usage ledger path.
+    pub fn load_unvalidated(repo_root: Option<&Path>, profile: Option<&str>) -> Result<Self> {
+        let mut paths: Vec<PathBuf> = Vec::new();
+        if let Some(dir) = config_dir() {
+            paths.extend(file::candidates(&dir, "config"));
+        }
+        let mut repo_file = file::FileConfig::default();
+        let mut repo_env = HashSet::new();
+        if let Some(root) = repo_root {
+            let repo_paths = file::candidates(root, ".fuckmit");
+            if !repo_paths.is_empty() {
+                repo_file = file::FileConfig::load(&repo_paths)?;
+                repo_file.check_repository_file()?;
+            }
+            paths.extend(repo_paths);
+            repo_env = load_dotenv(&root.join(".env"))?;
+            check_repository_env(&repo_env)?;
+        }
+
+        let profile = profile
+            .map(str::to_string)
+            .or_else(|| env::var("FUCKMIT_PROFILE").ok())
+            .filter(|name| !name.is_empty());
+
+        let mut file_config = file::FileConfig::load(&paths)?;
+        let selected = profile
+            .as_deref()
+            .map(|name| file_config.take_profile(name))
+            .transpose()?;
+
+        let mut config = Self::with_builtin_defaults();
+        file_config.apply(&mut config)?;
+        config.apply_env(&repo_env)?;
+        config.check_repository_base_urls(&repo_file, &repo_env)?;
+        if let Some(selected) = selected {
+            selected.apply(&mut config)?;
+            config.profile = profile;
+        }
+        Ok(config)
+    }
+
+    /// Configuration before any file or environment variable is applied
+    fn with_builtin_defaults() -> Self {
+        let mut config = Self::new(ProviderType::OpenAI); // Default to OpenAI
+        config.usage_ledger = UsageLedger::default_path();
+        config.cache_dir = ResponseCache::default_dir();
+        config
+    }
+
+    /// Select the default provider and model from a `provider/model` string
+    fn set_model(&mut self, provider_model: &str, source: &str) -> Result<()> {
+        let (provider_str, model_name) = provider_model.split_once('/').ok_or_else(|| {
+            GatewayError::Config(format!(
+                "{} must be in format 'provider/model' (e.g., 'openai/gpt-4')",
+                source
+            ))
+        })?;
+
+        let provider_type = provider_str.parse::<ProviderType>().map_err(|_| {
+            GatewayError::Config(format!("Invalid provider in {}: {}", source, provider_str))
+        })?;
+
+        self.default_provider = provider_type;
+        self.default_model = Some(model_name.to_string());
+        Ok(())
+    }
+
+    /// Override settings from environment variables
+    ///
+    /// `repo_env` names the variables that were set by the repository's
+    /// `.env` file.
+    fn apply_env(&mut self, repo_env: &HashSet<String>) -> Result<()> {
+        // Support provider/model format for unified model specification
+        if let Ok(provider_model) = env::var("LLM_MODEL") {
+            self.set_model(&provider_model, "LLM_MODEL")?;
+        }
+
+        // Load OpenAI configuration
+        if let Some(provider_config) =
+            self.env_provider(ProviderType::OpenAI, "OPENAI_API_KEY", repo_env)?
+        {
+            if let Ok(base_url) = env::var("OPENAI_BASE_URL") {
+                provider_config.base_url = Some(base_url);
+            }
+
+            if let Ok(model) = env::var("OPENAI_MODEL") {
+                provider_config.default_model = Some(model);
+            }
+        }
+
+        // Load Azure configuration
+        if let Some(provider_config) =
+            self.env_provider(ProviderType::Azure, "AZURE_OPENAI_API_KEY", repo_env)?
+        {
+            if let Ok(endpoint) = env::var("AZURE_OPENAI_ENDPOINT") {
+                provider_config.base_url = Some(endpoint);
+            }
+
+            if let Ok(deployment) = env::var("AZURE_OPENAI_DEPLOYMENT") {
+                provider_config.default_model = Some(deployment);
+            }
+
+            if let Ok(api_version) = env::var("AZURE_OPENAI_API_VERSION") {
+                provider_config
+                    .extra
+                    .insert("api_version".to_string(), api_version);
+            }
+        }
+
+        // Load DeepSeek configuration
+        if let Some(provider_config) =
+            self.env_provider(ProviderType::DeepSeek, "DEEPSEEK_API_KEY", repo_env)?
+        {
+            if let Ok(base_url) = env::var("DEEPSEEK_BASE_URL") {
+                provider_config.base_url = Some(base_url);
+            }
+
+            if let Ok(model) = env::var("DEEPSEEK_MODEL") {
+                provider_config.default_model = Some(model);
+            }
+        }
+
+        // Load Qwen configuration
+        if let Some(provider_config) =
+            self.env_provider(ProviderType::Qwen, "QWEN_API_KEY", repo_env)?
+        {
+            if let Ok(base_url) = env::var("QWEN_BASE_URL") {
+                provider_config.base_url = Some(base_url);
+            }
+
+            if let Ok(model) = env::var("QWEN_MODEL") {
+                provider_config.default_model = Some(model);
+            }
+        }
+
+        // Load Anthropic configuration
+        if let Some(provider_config) =
+            self.env_provider(ProviderType::Anthropic, "ANTHROPIC_API_KEY", repo_env)?
+        {
+            if let Ok(base_url) = env::var("ANTHROPIC_BASE_URL") {
+                provider_config.base_url = Some(base_url);
+            }
+
+            if let Ok(model) = env::var("ANTHROPIC_MODEL") {
+                provider_config.default_model = Some(model);
+            }
+
+            if let Ok(api_version) = env::var("ANTHROPIC_API_VERSION") {
+                provider_config
+                    .extra
+                    .insert("api_version".to_string(), api_version);
+            }
+        }
+
+        // Load Gemini configuration
+        if let Some(provider_config) =
+            self.env_provider(ProviderType::Gemini, "GEMINI_API_KEY", repo_env)?
+        {
+            if let Ok(base_url) = env::var("GEMINI_BASE_URL") {
+                provider_config.base_url = Some(base_url);
+            }
+
+            if let Ok(model) = env::var("GEMINI_MODEL") {
+                provider_config.default_model = Some(model);
+            }
+        }
+
+        // Load Ollama configuration (no API key required)
+        if self.default_provider == ProviderType::Ollama
+            || any_env_set(&[
+                "OLLAMA_BASE_URL",
+                "OLLAMA_MODEL",
+                "OLLAMA_NUM_CTX",
+                "OLLAMA_KEEP_ALIVE",
+            ])
+        {
+            self.providers
+                .entry(ProviderType::Ollama)
+                .or_insert_with(|| ProviderConfig::new(""));
+        }
+        if let Some(provider_config) =
+            self.env_provider(ProviderType::Ollama, "OLLAMA_API_KEY", repo_env)?
+        {
+            if let Ok(base_url) = env::var("OLLAMA_BASE_URL") {
+                provider_config.base_url = Some(base_url);
+            }
+
+            if let Ok(model) = env::var("OLLAMA_MODEL") {
+                provider_config.default_model = Some(model);
+            }
+
+            if let Ok(num_ctx) = env::var("OLLAMA_NUM_CTX") {
+                provider_config.extra.insert("num_ctx".to_string(), num_ctx);
+            }
+
+            if let Ok(keep_alive) = env::var("OLLAMA_KEEP_ALIVE") {
+                provider_config
+                    .extra
+                    .insert("keep_alive".to_string(), keep_alive);
+            }
+        }
+
+        // Load mock configuration (scripted replies, no API key required)
+        if self.default_provider == ProviderType::Mock
+            || any_env_set(&[
+                "MOCK_RESPONSE",
+                "MOCK_SCRIPT",
+                "MOCK_MODEL",
+                "MOCK_DELAY_MS",
+            ])
+        {
+            self.providers
+                .entry(ProviderType::Mock)
+                .or_insert_with(|| ProviderConfig::new(""));
+        }
+        if let Some(provider_config) = self.providers.get_mut(&ProviderType::Mock) {
+            if let Ok(model) = env::var("MOCK_MODEL") {
+                provider_config.default_model = Some(model);
+            }
+
+            for (var, key) in [
+                ("MOCK_RESPONSE", "response"),
+                ("MOCK_SCRIPT", "script"),
+                ("MOCK_DELAY_MS", "delay_ms"),
+            ] {
+                if let Ok(value) = env::var(var) {
+                    provider_config.extra.insert(key.to_string(), value);
+                }
+            }
+        }
+
+        // Load user-defined OpenAI-compatible providers
+        if let Ok(names) = env::var("LLM_CUSTOM_PROVIDERS") {
+            for name in names.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+                let provider_type = match name.parse::<ProviderType>() {
+                    Ok(provider_type @ ProviderType::Custom(_)) => provider_type,
+                    _ => {
+                        return Err(GatewayError::Config(format!(
+                            "Invalid custom provider name in LLM_CUSTOM_PROVIDERS: {}",
+                            name
+                        )))
+                    }
+                };
+
+                let prefix = env_prefix(&provider_type);
+                let var = |suffix: &str| env::var(format!("{}_{}", prefix, suffix));
+
+                let provider_config = self
+                    .providers
+                    .entry(provider_type)
+                    .or_insert_with(|| ProviderConfig::new(""));
+
+                if let Ok(base_url) = var("BASE_URL") {
+                    provider_config.base_url = Some(base_url);
+                } else if provider_config.base_url.is_none() {
+                    return Err(GatewayError::Config(format!(
+                        "{}_BASE_URL is required",
+                        prefix
+                    )));
+                }
+
+                if let Some(api_key) = env_api_key(&format!("{}_API_KEY", prefix), repo_env)? {
+                    provider_config.api_key = api_key;
+                }
+
+                if let Ok(model) = var("MODEL") {
+                    provider_config.default_model = Some(model);
+                }
+
+                if let Ok(auth) = var("AUTH") {
+                    provider_config.auth =
+                        AuthStyle::from_str(&auth).map_err(GatewayError::Config)?;
+                }
+
+                if let Ok(headers) = var("HEADERS") {
+                    parse_headers(
+                        &headers,
+                        &format!("{}_HEADERS", prefix),
+                        &mut provider_config.headers,
+                    )?;
+                }
+
+                for (suffix, key) in [("SUPPORTS_N", "supports_n"), ("JSON_SCHEMA", "json_schema")]
+                {
+                    if let Ok(value) = var(suffix) {
+                        provider_config.extra.insert(key.to_string(), value);
+                    }
+                }
+            }
+        }
+
+        // Load fallback chain
+        if let Ok(fallbacks) = env::var("LLM_FALLBACK_PROVIDERS") {
+            self.fallback_providers = parse_providers(&fallbacks, "LLM_FALLBACK_PROVIDERS")?;
+        }
+
+        // Load global settings
+        if let Ok(timeout) = env::var("LLM_TIMEOUT_SECONDS") {
+            self.timeout_seconds = Some(
+                timeout
+                    .parse()
+                    .map_err(|_| GatewayError::Config("Invalid timeout value".to_string()))?,
+            );
+        }
+
+        if let Ok(timeout) = env::var("LLM_CONNECT_TIMEOUT_SECONDS") {
+            self.connect_timeout_seconds =
+                Some(timeout.parse().map_err(|_| {
+                    GatewayError::Config("Invalid connect timeout value".to_string())
+                })?);
+        }
+
+        if let Ok(timeout) = env::var("LLM_READ_TIMEOUT_SECONDS") {
+            self.read_timeout_seconds = Some(
+                timeout
+                    .parse()
+                    .map_err(|_| GatewayError::Config("Invalid read timeout value".to_string()))?,
+            );
+        }
+
+        if let Ok(retries) = env::var("LLM_MAX_RETRIES") {
+            self.max_retries = Some(
+                retries
+                    .parse()
+                    .map_err(|_| GatewayError::Config("Invalid max retries value".to_string()))?,
+            );
+        }
+
+        // Load network settings
+        if let Ok(proxy) = env::var("LLM_PROXY") {
+            self.network.proxy = Some(proxy);
+        }
+
+        if let Ok(no_proxy) = env::var("LLM_NO_PROXY") {
+            self.network.no_proxy = Some(no_proxy);
+        }
+
+        if let Ok(paths) = env::var("LLM_CA_CERTS") {
+            self.network.ca_certs = paths
+                .split(',')
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .map(PathBuf::from)
+                .collect();
+        }
+
+        if let Ok(path) = env::var("LLM_CLIENT_CERT") {
+            self.network.client_cert = Some(PathBuf::from(path));
+        }
+
+        if let Ok(path) = env::var("LLM_CLIENT_KEY") {
+            self.network.client_key = Some(PathBuf::from(path));
+        }
+
+        if let Ok(user_agent) = env::var("LLM_USER_AGENT") {
+            self.network.user_agent = Some(user_agent);
+        }
+
+        if let Ok(headers) = env::var("LLM_HEADERS") {
+            parse_headers(&headers, "LLM_HEADERS", &mut self.headers)?;
+        }
+
+        // "off" disables the usage ledger and the response cache
+        if let Ok(path) = env::var("LLM_USAGE_LEDGER") {
+            self.usage_ledger = optional_path(path);
+        }
+
+        if let Ok(dir) = env::var("LLM_CACHE_DIR") {
+            self.cache_dir = optional_path(dir);
+        }
+
+        if let Ok(ttl) = env::var("LLM_CACHE_TTL_SECONDS") {
+            self.cache_ttl_seconds = Some(
+                ttl.parse()
+                    .map_err(|_| GatewayError::Config("Invalid cache TTL value".to_string()))?,
+            );
+        }
+
+        if let Ok(size) = env::var("LLM_CACHE_MAX_MB") {
+            let megabytes: u64 = size
+                .parse()
+                .map_err(|_| GatewayError::Config("Invalid cache size value".to_string()))?;
+            self.cache_max_bytes = Some(megabytes * 1024 * 1024);
+        }
+
+        // Load cassette for recording or replaying provider traffic
+        if let Ok(path) = env::var("LLM_CASSETTE") {
+            self.cassette = optional_path(path).map(CassetteConfig::new);
+        }
+
+        if let Ok(mode) = env::var("LLM_CASSETTE_MODE") {
+            let mode = CassetteMode::from_str(&mode).map_err(GatewayError::Config)?;
+            if let Some(cassette) = &mut self.cassette {
+                cassette.mode = mode;
+            }
+        }
+
+        // Load generation defaults
+        if let Ok(max_tokens) = env::var("LLM_MAX_TOKENS") {
+            self.generation.max_tokens = Some(
+                max_tokens
+                    .parse()
+                    .map_err(|_| GatewayError::Config("Invalid max tokens value".to_string()))?,
+            );
+        }
+
+        if let Ok(temperature) = env::var("LLM_TEMPERATURE") {
+            self.generation.temperature = Some(
+                temperature
+                    .parse()
+                    .map_err(|_| GatewayError::Config("Invalid temperature value".to_string()))?,
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Provider whose API key is given by `key_var`, or that is already configured by a file
+    fn env_provider(
+        &mut self,
+        provider_type: ProviderType,
+        key_var: &str,
+        repo_env: &HashSet<String>,
+    ) -> Result<Option<&mut ProviderConfig>> {
+        Ok(match env_api_key(key_var, repo_env)? {
+            Some(api_key) => {
+                let provider_config = self
+                    .providers
+                    .entry(provider_type)
+                    .or_insert_with(|| ProviderConfig::new(""));
+                provider_config.api_key = api_key;
+                Some(provider_config)
+            }
+            None => self.providers.get_mut(&provider_type),
+        })
+    }
+
+    /// Reject base URLs the repository sets for API keys it does not set
+    ///
+    /// Otherwise a cloned repository could point a provider at its own
+    /// server and receive the user's API key.
+    fn check_repository_base_urls(
+        &self,
+        repo_file: &file::FileConfig,
+        repo_env: &HashSet<String>,
+    ) -> Result<()> {
+        let custom_providers = env::var("LLM_CUSTOM_PROVIDERS").unwrap_or_default();
+        for (provider_type, provider_config) in &self.providers {
+            if provider_config.base_url.is_none() || provider_config.api_key.is_empty() {
+                continue;
+            }
+
+            // The environment overrides the config files, except for custom
+            // providers that LLM_CUSTOM_PROVIDERS does not list
+            let reads_env = !matches!(provider_type, ProviderType::Custom(_))
+                || custom_providers
+                    .split(',')
+                    .any(|name| name.trim().parse().ok().as_ref() == Some(provider_type));
+            let from_repository = |vars: &[String], in_repo_file: bool| match vars
+                .iter()
+                .find(|var| reads_env && env::var_os(var).is_some())
+            {
+                Some(var) => repo_env.contains(var),
+                None => in_repo_file,
+            };
+
+            let prefix = env_prefix(provider_type);
+            let base_url_var = match provider_type {
+                ProviderType::Azure => format!("{}_ENDPOINT", prefix),
+                _ => format!("{}_BASE_URL", prefix),
+            };
+            let key_vars: Vec<String> = ["", "_CMD", "_FILE", "_CREDENTIAL"]
+                .iter()
+                .map(|suffix| format!("{}_API_KEY{}", prefix, suffix))
+                .collect();
+
+            if from_repository(&[base_url_var], repo_file.sets_base_url(provider_type))
+                && !from_repository(&key_vars, repo_file.sets_api_key(provider_type))
+            {
+                return Err(GatewayError::Config(format!(
+                    "The repository configuration sets the base URL of {}, whose API key is \
+                     configured elsewhere. Set the base URL in the user config file or the \
+                     environment instead.",
+                    provider_type
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Apply the selected model and check that the selected providers are configured
+    fn finalize(&mut self) -> Result<()> {
+        // Providers without an API key work with their defaults, whichever
+        // layer selected them
+        let selected = std::iter::once(&self.default_provider).chain(&self.fallback_providers);
+        for provider_type in selected {
+            if matches!(provider_type, ProviderType::Ollama | ProviderType::Mock) {
+                self.providers
+                    .entry(provider_type.clone())
+                    .or_insert_with(|| ProviderConfig::new(""));
+            }
+        }
+
+        // The selected model applies to the default provider
+        if let Some(model) = &self.default_model {
+            if let Some(provider_config) = self.providers.get_mut(&self.default_provider) {
+                provider_config.default_model = Some(model.clone());
+            }
+        }
+
+        // Validate that we have at least one provider configured
+        if self.providers.is_empty() {
+            return Err(GatewayError::Config(
+                "No providers configured. Please set at least one provider's API key.".to_string(),
+            ));
+        }
+
+        // Ensure the default provider is configured
+        if !self.providers.contains_key(&self.default_provider) {
+            return Err(GatewayError::Config(format!(
+                "Default provider {:?} is not configured",
+                self.default_provider
+            )));
+        }
+
+        // Ensure every fallback provider is configured
+        if let Some(missing) = self
+            .fallback_providers
+            .iter()
+            .find(|provider_type| !self.providers.contains_key(provider_type))
+        {
+            return Err(GatewayError::Config(format!(
+                "Fallback provider {:?} is not configured",
+                missing
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Get configuration for a specific provider
+    pub fn get_provider_config(&self, provider_type: &ProviderType) -> Option<&ProviderConfig> {
+        self.providers.get(provider_type)
+    }
+
+    /// Get the default provider configuration
+    pub fn get_default_provider_config(&self) -> Option<&ProviderConfig> {
+        self.get_provider_config(&self.default_provider)
+    }
+}
+
+/// Directory holding the user config file (`~/.config/fuckmit`)
+pub fn config_dir() -> Option<PathBuf> {
+    env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .or_else(|| dirs::home_dir().map(|home| home.join(".config")))
+        .map(|dir| dir.join("fuckmit"))
+}
+
+/// Parse a comma-separated list of providers
+fn parse_providers(list: &str, source: &str) -> Result<Vec<ProviderType>> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|provider_str| {
+            provider_str.parse::<ProviderType>().map_err(|_| {
+                GatewayError::Config(format!("Invalid provider in {}: {}", source, provider_str))
+            })
+        })
+        .collect()
+}
+
+/// Parse comma-separated `Name=value` headers into `headers`
+fn parse_headers(list: &str, source: &str, headers: &mut HashMap<String, String>) -> Result<()> {
+    for header in list.split(',').filter(|s| !s.trim().is_empty()) {
+        let (name, value) = header.split_once('=').ok_or_else(|| {
+            GatewayError::Config(format!("Invalid header in {}: {}", source, header))
+        })?;
+        headers.insert(name.trim().to_string(), value.trim().to_string());
+    }
+    Ok(())
+}
+
+/// A path setting where "off" means disabled
+fn optional_path(value: String) -> Option<PathBuf> {
+    (!value.eq_ignore_ascii_case("off")).then(|| PathBuf::from(value))
+}
+
+/// API key from `key_var`, or from `<key_var>_CMD`, `_FILE` or `_CREDENTIAL`
+///
+/// The `_CMD`, `_FILE` and `_CREDENTIAL` forms are rejected when they come
+/// from the repository's `.env` file (`repo_env`).
+fn env_api_key(key_var: &str, repo_env: &HashSet<String>) -> Result<Option<ApiKey>> {
+    if let Ok(value) = env::var(key_var) {
+        return Ok(Some(ApiKey::new(value)));
+    }
+
+    for suffix in ["_CMD", "_FILE", "_CREDENTIAL"] {
+        let name = format!("{}{}", key_var, suffix);
+        let Ok(value) = env::var(&name) else {
+            continue;
+        };
+        if repo_env.contains(&name) {
+            return Err(GatewayError::Config(format!(
+                "{} is only allowed in the environment, not in the repository's .env file",
+                name
+            )));
+        }
+        let source = match suffix {
+            "_CMD" => KeySource::Command(value),
+            "_FILE" => KeySource::File(value.into()),
+            _ => KeySource::Credential(value),
+        };
+        return Ok(Some(ApiKey::from_source(source)));
+    }
+    Ok(None)
+}
+
+/// Variables the repository's `.env` file must not set
+///
+/// Proxies, certificates and extra headers would let a cloned repository
+/// intercept requests and their API keys, and the file locations and the
+/// passphrase would let it choose where fuckmit writes and what it decrypts.
+const USER_ONLY_ENV: &[&str] = &[
+    "LLM_PROXY",
+    "LLM_CA_CERTS",
+    "LLM_CLIENT_CERT",
+    "LLM_CLIENT_KEY",
+    "LLM_HEADERS",
+    "LLM_USAGE_LEDGER",
+    "LLM_CACHE_DIR",
+    "LLM_CASSETTE",
+    "FUCKMIT_CREDENTIALS_FILE",
+    "FUCKMIT_PASSPHRASE",
+];
+
+/// Reject variables from the repository's `.env` file (`repo_env`) that
+/// only the user may set, including every provider's `<PREFIX>_HEADERS`
+fn check_repository_env(repo_env: &HashSet<String>) -> Result<()> {
+    match repo_env
+        .iter()
+        .find(|name| USER_ONLY_ENV.contains(&name.as_str()) || name.ends_with("_HEADERS"))
+    {
+        Some(name) => Err(GatewayError::Config(format!(
+            "{} is only allowed in the environment, not in the repository's .env file",
+            name
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Whether any of `vars` is set
+fn any_env_set(vars: &[&str]) -> bool {
+    vars.iter().any(|var| env::var_os(var).is_some())
+}
+
+/// Prefix of the environment variables of a provider (`OPENAI`, `AZURE_OPENAI`, ...)
+fn env_prefix(provider_type: &ProviderType) -> String {
+    match provider_type {
+        ProviderType::Azure => "AZURE_OPENAI".to_string(),
+        _ => provider_type.to_string().to_uppercase().replace('-', "_"),
+    }
+}
+
+/// Load the `.env` file at `path` and return the names of the variables it set
+///
+/// Variables already set in the environment win over the file.
+fn load_dotenv(path: &Path) -> Result<HashSet<String>> {
+    let invalid = |err: dotenvy::Error| GatewayError::Config(format!("Invalid .env file: {}", err));
+    let entries = match dotenvy::from_path_iter(path) {
+        Ok(entries) => entries,
+        Err(dotenvy::Error::Io(err)) if err.kind() == std::io::ErrorKind::NotFound => {
+            return Ok(HashSet::new())
+        }
+        Err(err) => return Err(invalid(err)),
+    };
+
+    let mut set = HashSet::new();
+    for entry in entries {
+        let (name, value) = entry.map_err(invalid)?;
+        if env::var_os(&name).is_none() {
+            env::set_var(&name, value);
+            set.insert(name);
+        }
+    }
+    Ok(set)
+}
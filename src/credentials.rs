@@ -0,0 +1,388 @@
+//! API key sources and the encrypted credentials file
+//!
+//! A key can be given directly, printed by a command (`pass show openai`),
+//! read from a file, or stored in a credentials file encrypted with a
+//! passphrase. Keys are resolved on first use and never printed by `Debug`.
+
+use crate::config;
+use crate::error::{GatewayError, Result};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, CHACHA20_POLY1305, NONCE_LEN};
+use ring::pbkdf2;
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::num::NonZeroU32;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{Arc, Mutex, OnceLock};
+
+/// PBKDF2 rounds used for new credentials files
+const PBKDF2_ITERATIONS: u32 = 600_000;
+
+/// Length of the random salt, in bytes
+const SALT_LEN: usize = 16;
+
+/// Credentials unlocked so far in this process, keyed by file
+static UNLOCKED: Mutex<BTreeMap<PathBuf, BTreeMap<String, String>>> = Mutex::new(BTreeMap::new());
+
+/// Where an API key comes from
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeySource {
+    /// The key itself
+    Value(String),
+    /// Shell command that prints the key
+    Command(String),
+    /// File containing the key
+    File(PathBuf),
+    /// Entry in the encrypted credentials file
+    Credential(String),
+}
+
+impl fmt::Debug for KeySource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeySource::Value(value) if value.is_empty() => write!(f, "Value(\"\")"),
+            KeySource::Value(_) => write!(f, "Value(<redacted>)"),
+            KeySource::Command(command) => f.debug_tuple("Command").field(command).finish(),
+            KeySource::File(path) => f.debug_tuple("File").field(path).finish(),
+            KeySource::Credential(name) => f.debug_tuple("Credential").field(name).finish(),
+        }
+    }
+}
+
+/// API key that is resolved from its source on first use
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(from = "KeyRepr", into = "KeyRepr")]
+pub struct ApiKey {
+    source: KeySource,
+    resolved: Arc<OnceLock<String>>,
+}
+
+/// Keys are written as a plain string or as `{ command = "..." }` and similar
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum KeyRepr {
+    Plain(String),
+    Source(KeySource),
+}
+
+impl From<KeyRepr> for ApiKey {
+    fn from(repr: KeyRepr) -> Self {
+        match repr {
+            KeyRepr::Plain(value) => ApiKey::new(value),
+            KeyRepr::Source(source) => ApiKey::from_source(source),
+        }
+    }
+}
+
+impl From<ApiKey> for KeyRepr {
+    fn from(key: ApiKey) -> Self {
+        match key.source {
+            KeySource::Value(value) => KeyRepr::Plain(value),
+            source => KeyRepr::Source(source),
+        }
+    }
+}
+
+impl ApiKey {
+    /// A key given directly
+    pub fn new(value: impl Into<String>) -> Self {
+        Self::from_source(KeySource::Value(value.into()))
+    }
+
+    /// A key read from `source` when first needed
+    pub fn from_source(source: KeySource) -> Self {
+        Self {
+            source,
+            resolved: Arc::new(OnceLock::new()),
+        }
+    }
+
+    /// Where the key comes from
+    pub fn source(&self) -> &KeySource {
+        &self.source
+    }
+
+    /// Whether no key is configured
+    pub fn is_empty(&self) -> bool {
+        matches!(&self.source, KeySource::Value(value) if value.is_empty())
+    }
+
+    /// The key, resolved on a blocking thread on first call
+    ///
+    /// Running the key command, asking for the passphrase and deriving the
+    /// credentials key can all take a while, so they stay off the async runtime.
+    pub async fn resolve(&self) -> Result<String> {
+        if let Some(key) = self.resolved.get() {
+            return Ok(key.clone());
+        }
+
+        let key = self.clone();
+        tokio::task::spawn_blocking(move || key.resolve_blocking())
+            .await
+            .map_err(|e| GatewayError::Auth(format!("Failed to resolve API key: {}", e)))?
+    }
+
+    /// The key, running the command or reading the file on first call
+    pub fn resolve_blocking(&self) -> Result<String> {
+        if let Some(key) = self.resolved.get() {
+            return Ok(key.clone());
+        }
+
+        let key = match &self.source {
+            KeySource::Value(value) => value.clone(),
+            KeySource::Command(command) => run_key_command(command)?,
+            KeySource::File(path) => fs::read_to_string(expand_home(path))
+                .map_err(|e| {
+                    GatewayError::Auth(format!(
+                        "Failed to read API key file {}: {}",
+                        path.display(),
+                        e
+                    ))
+                })?
+                .trim()
+                .to_string(),
+            KeySource::Credential(name) => CredentialStore::from_env()?.get(name)?,
+        };
+
+        if key.is_empty() && !self.is_empty() {
+            return Err(GatewayError::Auth(format!(
+                "API key source {:?} produced an empty key",
+                self.source
+            )));
+        }
+
+        Ok(self.resolved.get_or_init(|| key).clone())
+    }
+}
+
+impl Default for ApiKey {
+    fn default() -> Self {
+        Self::new("")
+    }
+}
+
+impl PartialEq for ApiKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.source == other.source
+    }
+}
+
+impl fmt::Debug for ApiKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.source.fmt(f)
+    }
+}
+
+impl From<String> for ApiKey {
+    fn from(value: String) -> Self {
+        ApiKey::new(value)
+    }
+}
+
+impl From<&str> for ApiKey {
+    fn from(value: &str) -> Self {
+        ApiKey::new(value)
+    }
+}
+
+/// Run a key command through the shell and return its trimmed output
+fn run_key_command(command: &str) -> Result<String> {
+    let output = if cfg!(windows) {
+        Command::new("cmd").args(["/C", command]).output()
+    } else {
+        Command::new("sh").args(["-c", command]).output()
+    }
+    .map_err(|e| GatewayError::Auth(format!("Failed to run API key command: {}", e)))?;
+
+    if !output.status.success() {
+        return Err(GatewayError::Auth(format!(
+            "API key command `{}` failed: {}",
+            command,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    String::from_utf8(output.stdout)
+        .map(|key| key.trim().to_string())
+        .map_err(|_| GatewayError::Auth("API key command printed invalid UTF-8".to_string()))
+}
+
+/// Expand a leading `~/` to the home directory
+fn expand_home(path: &Path) -> PathBuf {
+    match (path.strip_prefix("~"), dirs::home_dir()) {
+        (Ok(rest), Some(home)) => home.join(rest),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// Credentials file contents as stored on disk
+#[derive(Serialize, Deserialize)]
+struct EncryptedFile {
+    version: u32,
+    iterations: u32,
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+/// Credentials file encrypted with ChaCha20-Poly1305 and a PBKDF2-derived key
+#[derive(Debug, Clone)]
+pub struct CredentialStore {
+    path: PathBuf,
+}
+
+impl CredentialStore {
+    /// Credentials file at `path`
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// Credentials file from `FUCKMIT_CREDENTIALS_FILE`, or the default location
+    pub fn from_env() -> Result<Self> {
+        env::var_os("FUCKMIT_CREDENTIALS_FILE")
+            .map(PathBuf::from)
+            .or_else(Self::default_path)
+            .map(Self::new)
+            .ok_or_else(|| {
+                GatewayError::Config("Could not determine the credentials file path".to_string())
+            })
+    }
+
+    /// Default location (`~/.config/fuckmit/credentials.enc`)
+    pub fn default_path() -> Option<PathBuf> {
+        config::config_dir().map(|dir| dir.join("credentials.enc"))
+    }
+
+    /// Path of the credentials file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Whether the credentials file exists
+    pub fn exists(&self) -> bool {
+        self.path.is_file()
+    }
+
+    /// Look up a credential, asking for the passphrase if the file is still locked
+    pub fn get(&self, name: &str) -> Result<String> {
+        let mut unlocked = UNLOCKED.lock().unwrap_or_else(|e| e.into_inner());
+        if !unlocked.contains_key(&self.path) {
+            let credentials = self.decrypt(&passphrase("Credentials passphrase: ")?)?;
+            unlocked.insert(self.path.clone(), credentials);
+        }
+
+        unlocked[&self.path].get(name).cloned().ok_or_else(|| {
+            GatewayError::Auth(format!(
+                "No credential named {:?} in {}",
+                name,
+                self.path.display()
+            ))
+        })
+    }
+
+    /// Decrypt all credentials; a missing file holds none
+    pub fn decrypt(&self, passphrase: &str) -> Result<BTreeMap<String, String>> {
+        let content = match fs::read(&self.path) {
+            Ok(content) => content,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(BTreeMap::new()),
+            Err(err) => return Err(err.into()),
+        };
+
+        let file: EncryptedFile = serde_json::from_slice(&content)?;
+        let invalid =
+            || GatewayError::Config(format!("Corrupt credentials file {}", self.path.display()));
+        let salt = BASE64.decode(&file.salt).map_err(|_| invalid())?;
+        let nonce = BASE64.decode(&file.nonce).map_err(|_| invalid())?;
+        let mut data = BASE64.decode(&file.ciphertext).map_err(|_| invalid())?;
+
+        let nonce = Nonce::try_assume_unique_for_key(&nonce).map_err(|_| invalid())?;
+        let key = derive_key(passphrase, &salt, file.iterations)?;
+        let plaintext = key
+            .open_in_place(nonce, Aad::empty(), &mut data)
+            .map_err(|_| GatewayError::Auth("Wrong credentials passphrase".to_string()))?;
+
+        Ok(serde_json::from_slice(plaintext)?)
+    }
+
+    /// Encrypt and write all credentials
+    pub fn encrypt(&self, passphrase: &str, credentials: &BTreeMap<String, String>) -> Result<()> {
+        let rng = SystemRandom::new();
+        let mut salt = [0u8; SALT_LEN];
+        let mut nonce = [0u8; NONCE_LEN];
+        rng.fill(&mut salt)
+            .and_then(|_| rng.fill(&mut nonce))
+            .map_err(|_| GatewayError::Other("Failed to generate random bytes".to_string()))?;
+
+        let key = derive_key(passphrase, &salt, PBKDF2_ITERATIONS)?;
+        let mut data = serde_json::to_vec(credentials)?;
+        key.seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), Aad::empty(), &mut data)
+            .map_err(|_| GatewayError::Other("Failed to encrypt credentials".to_string()))?;
+
+        let file = EncryptedFile {
+            version: 1,
+            iterations: PBKDF2_ITERATIONS,
+            salt: BASE64.encode(salt),
+            nonce: BASE64.encode(nonce),
+            ciphertext: BASE64.encode(data),
+        };
+
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&self.path, serde_json::to_vec_pretty(&file)?)?;
+        restrict_permissions(&self.path)?;
+
+        UNLOCKED
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(self.path.clone(), credentials.clone());
+        Ok(())
+    }
+}
+
+fn derive_key(passphrase: &str, salt: &[u8], iterations: u32) -> Result<LessSafeKey> {
+    let iterations = NonZeroU32::new(iterations)
+        .ok_or_else(|| GatewayError::Config("Invalid credentials file iterations".to_string()))?;
+
+    let mut key = [0u8; 32];
+    pbkdf2::derive(
+        pbkdf2::PBKDF2_HMAC_SHA256,
+        iterations,
+        salt,
+        passphrase.as_bytes(),
+        &mut key,
+    );
+
+    UnboundKey::new(&CHACHA20_POLY1305, &key)
+        .map(LessSafeKey::new)
+        .map_err(|_| GatewayError::Other("Failed to create encryption key".to_string()))
+}
+
+#[cfg(unix)]
+fn restrict_permissions(path: &Path) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn restrict_permissions(_path: &Path) -> Result<()> {
+    Ok(())
+}
+
+/// Passphrase from `FUCKMIT_PASSPHRASE`, or read from the terminal
+pub fn passphrase(prompt: &str) -> Result<String> {
+    if let Ok(passphrase) = env::var("FUCKMIT_PASSPHRASE") {
+        return Ok(passphrase);
+    }
+
+    rpassword::prompt_password(prompt)
+        .map_err(|e| GatewayError::Auth(format!("Failed to read passphrase: {}", e)))
+}
@@ -1,6 +1,8 @@
 //! Error types for the LLM Gateway library
 
+use reqwest::StatusCode;
 use std::fmt;
+use std::time::Duration;
 
 /// Result type alias for the gateway
 pub type Result<T> = std::result::Result<T, GatewayError>;
@@ -14,8 +16,24 @@ pub enum GatewayError {
     Http(reqwest::Error),
     /// JSON serialization/deserialization error
     Json(serde_json::Error),
+    /// Local file error
+    Io(std::io::Error),
     /// Provider-specific error
     Provider(String),
+    /// Provider rejected the API key or denied access (401, 403)
+    Unauthorized(Box<ApiError>),
+    /// Provider rate limit or quota was hit (429)
+    RateLimited(Box<ApiError>),
+    /// Prompt or completion was blocked by the provider's content filter
+    ContentFiltered(Box<ApiError>),
+    /// Prompt does not fit the model's context window
+    ContextLengthExceeded(Box<ApiError>),
+    /// Model, deployment or endpoint does not exist (404)
+    NotFound(Box<ApiError>),
+    /// Provider rejected the request as invalid (other 4xx)
+    BadRequest(Box<ApiError>),
+    /// Any other non-success status returned by a provider API
+    Api(Box<ApiError>),
     /// Authentication error
     Auth(String),
     /// Rate limit error
@@ -24,6 +42,8 @@ pub enum GatewayError {
     InvalidRequest(String),
     /// Network timeout error
     Timeout(String),
+    /// Reply did not have the expected format
+    InvalidResponse(String),
     /// Generic error
     Other(String),
 }
@@ -34,21 +54,122 @@ impl fmt::Display for GatewayError {
             GatewayError::Config(msg) => write!(f, "Configuration error: {}", msg),
             GatewayError::Http(err) => write!(f, "HTTP error: {}", err),
             GatewayError::Json(err) => write!(f, "JSON error: {}", err),
+            GatewayError::Io(err) => write!(f, "IO error: {}", err),
             GatewayError::Provider(msg) => write!(f, "Provider error: {}", msg),
+            GatewayError::Unauthorized(err) => write!(f, "Authentication failed: {}", err),
+            GatewayError::RateLimited(err) => write!(f, "Rate limited: {}", err),
+            GatewayError::ContentFiltered(err) => write!(f, "Content filtered: {}", err),
+            GatewayError::ContextLengthExceeded(err) => {
+                write!(f, "Context length exceeded: {}", err)
+            }
+            GatewayError::NotFound(err) => write!(f, "Not found: {}", err),
+            GatewayError::BadRequest(err) => write!(f, "Bad request: {}", err),
+            GatewayError::Api(err) => write!(f, "{}", err),
             GatewayError::Auth(msg) => write!(f, "Authentication error: {}", msg),
             GatewayError::RateLimit(msg) => write!(f, "Rate limit error: {}", msg),
             GatewayError::InvalidRequest(msg) => write!(f, "Invalid request: {}", msg),
             GatewayError::Timeout(msg) => write!(f, "Timeout error: {}", msg),
+            GatewayError::InvalidResponse(msg) => write!(f, "Invalid response: {}", msg),
             GatewayError::Other(msg) => write!(f, "Error: {}", msg),
         }
     }
 }
 
+/// Error response returned by a provider API
+#[derive(Debug, Clone)]
+pub struct ApiError {
+    /// Provider name
+    pub provider: String,
+    /// HTTP status code
+    pub status: StatusCode,
+    /// Machine-readable error code (e.g. `insufficient_quota`, `content_filter`)
+    pub code: Option<String>,
+    /// Error type or category reported by the provider
+    pub error_type: Option<String>,
+    /// Human-readable message, or the raw body if it could not be parsed
+    pub message: String,
+    /// Request ID to quote to the provider's support
+    pub request_id: Option<String>,
+    /// Delay requested by the server through `Retry-After`
+    pub retry_after: Option<Duration>,
+    /// Additional structured details, such as Azure content filter results
+    pub details: Option<serde_json::Value>,
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} API error {}", self.provider, self.status)?;
+        if let Some(code) = &self.code {
+            write!(f, " ({})", code)?;
+        }
+        write!(f, ": {}", self.message)?;
+        if let Some(request_id) = &self.request_id {
+            write!(f, " [request id {}]", request_id)?;
+        }
+        Ok(())
+    }
+}
+
+impl GatewayError {
+    /// Details of an error response from a provider API, if this is one
+    pub fn api_error(&self) -> Option<&ApiError> {
+        match self {
+            GatewayError::Unauthorized(err)
+            | GatewayError::RateLimited(err)
+            | GatewayError::ContentFiltered(err)
+            | GatewayError::ContextLengthExceeded(err)
+            | GatewayError::NotFound(err)
+            | GatewayError::BadRequest(err)
+            | GatewayError::Api(err) => Some(err.as_ref()),
+            _ => None,
+        }
+    }
+
+    /// Whether the failure is transient and the request may be retried
+    ///
+    /// Timeouts, connection failures, rate limits and server errors are
+    /// retryable; authentication and validation errors are not.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            GatewayError::Timeout(_) | GatewayError::RateLimit(_) => true,
+            GatewayError::Http(err) => {
+                err.is_timeout()
+                    || err.is_connect()
+                    || err.is_request()
+                    || err.status().is_some_and(is_retryable_status)
+            }
+            // An exhausted quota does not recover by waiting
+            GatewayError::RateLimited(err) => !is_quota_error(err),
+            GatewayError::Api(err) => is_retryable_status(err.status),
+            _ => false,
+        }
+    }
+
+    /// Delay requested by the server before retrying, if any
+    pub fn retry_after(&self) -> Option<Duration> {
+        self.api_error().and_then(|err| err.retry_after)
+    }
+}
+
+fn is_quota_error(err: &ApiError) -> bool {
+    matches!(
+        err.code.as_deref(),
+        Some("insufficient_quota" | "billing_hard_limit_reached" | "Arrearage")
+    )
+}
+
+fn is_retryable_status(status: StatusCode) -> bool {
+    status == StatusCode::REQUEST_TIMEOUT
+        || status == StatusCode::TOO_MANY_REQUESTS
+        || status.is_server_error()
+}
+
 impl std::error::Error for GatewayError {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match self {
             GatewayError::Http(err) => Some(err),
             GatewayError::Json(err) => Some(err),
+            GatewayError::Io(err) => Some(err),
             _ => None,
         }
     }
@@ -79,6 +200,12 @@ impl From<serde_json::Error> for GatewayError {
     }
 }
 
+impl From<std::io::Error> for GatewayError {
+    fn from(err: std::io::Error) -> Self {
+        GatewayError::Io(err)
+    }
+}
+
 impl From<std::env::VarError> for GatewayError {
     fn from(err: std::env::VarError) -> Self {
         GatewayError::Config(format!("Environment variable error: {}", err))
@@ -1,16 +1,38 @@
 //! Main gateway implementation
 
+use crate::cache::{CacheMode, ResponseCache};
 use crate::config::GatewayConfig;
-use crate::error::{GatewayError, Result};
+use crate::error::{ApiError, GatewayError, Result};
+use crate::middleware::{Middleware, RequestContext};
 use crate::providers::{create_provider, Provider, ProviderType};
-use crate::types::{ChatMessage, ChatRequest, ChatResponse};
+use crate::retry::RetryPolicy;
+use crate::schema;
+use crate::stream::{single_chunk, ChatStream};
+use crate::types::{
+    ChatMessage, ChatRequest, ChatResponse, MessageRole, ModelInfo, ResponseFormat,
+};
+use crate::usage::{UsageLedger, UsageRecord};
+use futures::{future, StreamExt};
+use reqwest::StatusCode;
+use serde::de::DeserializeOwned;
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn, Instrument};
+
+/// Number of replies `chat_structured` asks for before giving up
+const STRUCTURED_MAX_ATTEMPTS: usize = 3;
 
 /// Main LLM Gateway struct
 pub struct LLMGateway {
     config: GatewayConfig,
     providers: HashMap<ProviderType, Arc<dyn Provider>>,
+    retry: RetryPolicy,
+    ledger: Option<UsageLedger>,
+    cache: Option<ResponseCache>,
+    cache_mode: CacheMode,
+    cache_sampled: bool,
+    middleware: Vec<Arc<dyn Middleware>>,
 }
 
 impl LLMGateway {
@@ -20,12 +42,73 @@ impl LLMGateway {
 
         // Initialize all configured providers
         for (provider_type, provider_config) in &config.providers {
-            let provider = create_provider(*provider_type, provider_config)?;
+            let provider_config = provider_config.clone().inherit(&config);
+            let provider = create_provider(provider_type.clone(), &provider_config)?;
             provider.validate_config()?;
-            providers.insert(*provider_type, Arc::from(provider));
+            providers.insert(provider_type.clone(), Arc::from(provider));
         }
 
-        Ok(Self { config, providers })
+        let retry = config.max_retries.map(RetryPolicy::new).unwrap_or_default();
+        let ledger = config.usage_ledger.clone().map(UsageLedger::new);
+        let cache = config.cache_dir.clone().map(|dir| {
+            let mut cache = ResponseCache::new(dir);
+            if let Some(ttl) = config.cache_ttl_seconds {
+                cache = cache.with_ttl(Duration::from_secs(ttl));
+            }
+            if let Some(max_bytes) = config.cache_max_bytes {
+                cache = cache.with_max_bytes(max_bytes);
+            }
+            cache
+        });
+
+        Ok(Self {
+            config,
+            providers,
+            retry,
+            ledger,
+            cache,
+            cache_mode: CacheMode::default(),
+            cache_sampled: false,
+            middleware: Vec::new(),
+        })
+    }
+
+    /// Set how the response cache is used
+    ///
+    /// The cache is bypassed unless a mode is set.
+    pub fn with_cache_mode(mut self, cache_mode: CacheMode) -> Self {
+        self.cache_mode = cache_mode;
+        self
+    }
+
+    /// Also cache requests that sample (temperature above 0 or several choices)
+    ///
+    /// Repeating such a request then returns the cached reply instead of a new sample.
+    pub fn with_cache_sampled(mut self, cache_sampled: bool) -> Self {
+        self.cache_sampled = cache_sampled;
+        self
+    }
+
+    /// Use `provider` for its provider type, replacing a configured one
+    ///
+    /// This is how a scripted [`MockProvider`](crate::providers::mock::MockProvider)
+    /// is put in place for tests.
+    pub fn with_provider(mut self, provider: impl Provider + 'static) -> Self {
+        self.providers
+            .insert(provider.provider_type(), Arc::new(provider));
+        self
+    }
+
+    /// Add middleware that runs around every provider call
+    pub fn with_middleware(mut self, middleware: impl Middleware + 'static) -> Self {
+        self.middleware.push(Arc::new(middleware));
+        self
+    }
+
+    /// Tag the calls recorded in the usage ledger with a repository name
+    pub fn with_repository(mut self, repository: impl Into<String>) -> Self {
+        self.ledger = self.ledger.map(|ledger| ledger.with_repository(repository));
+        self
     }
 
     /// Create a gateway from environment variables
@@ -56,7 +139,7 @@ impl LLMGateway {
         provider_type: Option<ProviderType>,
         model: Option<String>,
     ) -> Result<ChatResponse> {
-        let provider_type = provider_type.unwrap_or(self.config.default_provider);
+        let provider_type = provider_type.unwrap_or_else(|| self.config.default_provider.clone());
         let provider = self.get_provider(&provider_type)?;
 
         // Use specified model or provider's default model
@@ -68,7 +151,7 @@ impl LLMGateway {
         });
 
         let request = ChatRequest::new(messages, model_name);
-        provider.chat_completion(request).await
+        self.send(provider_type, request).await
     }
 
     /// Send a custom chat request
@@ -82,9 +165,108 @@ impl LLMGateway {
         request: ChatRequest,
         provider_type: Option<ProviderType>,
     ) -> Result<ChatResponse> {
-        let provider_type = provider_type.unwrap_or(self.config.default_provider);
-        let provider = self.get_provider(&provider_type)?;
-        provider.chat_completion(request).await
+        let provider_type = provider_type.unwrap_or_else(|| self.config.default_provider.clone());
+        self.send(provider_type, request).await
+    }
+
+    /// Send a streaming chat completion request using the default provider
+    pub async fn chat_completion_stream(&self, messages: Vec<ChatMessage>) -> Result<ChatStream> {
+        let provider = self.get_provider(&self.config.default_provider)?;
+        let model_name = provider
+            .default_model()
+            .unwrap_or("gpt-3.5-turbo")
+            .to_string();
+
+        self.chat_request_stream(ChatRequest::new(messages, model_name))
+            .await
+    }
+
+    /// Send a custom streaming chat request
+    pub async fn chat_request_stream(&self, request: ChatRequest) -> Result<ChatStream> {
+        self.chat_request_stream_with_provider(request, None).await
+    }
+
+    /// Send a custom streaming chat request with a specific provider
+    ///
+    /// Providers that cannot stream are called normally and their response
+    /// is delivered as a single chunk. Requests for several choices (`n`)
+    /// fail on providers that do not support `n`.
+    pub async fn chat_request_stream_with_provider(
+        &self,
+        request: ChatRequest,
+        provider_type: Option<ProviderType>,
+    ) -> Result<ChatStream> {
+        let provider_type = provider_type.unwrap_or_else(|| self.config.default_provider.clone());
+        let mut last_error = None;
+
+        for (index, (provider_type, provider)) in
+            self.fallback_chain(provider_type)?.into_iter().enumerate()
+        {
+            let mut request = Self::fit_budget(
+                &provider_type,
+                Self::fallback_request(index, &provider, &request),
+            )?;
+            if !provider.supports_n() {
+                if let Some(n @ 2..) = request.n {
+                    return Err(GatewayError::InvalidRequest(format!(
+                        "{} cannot stream several choices (n = {})",
+                        provider_type, n
+                    )));
+                }
+                request.n = None;
+            }
+            let started = Instant::now();
+            let result = if provider.supports_streaming() {
+                self.retry
+                    .run(|| self.call_provider_stream(&provider, request.clone()))
+                    .await
+            } else {
+                self.retry
+                    .run(|| self.call_provider(&provider, request.clone()))
+                    .await
+                    .map(single_chunk)
+            };
+
+            match result {
+                Ok(stream) => {
+                    let Some(ledger) = self.ledger.clone() else {
+                        return Ok(stream);
+                    };
+                    // Usage arrives with the final chunk
+                    let model = request.model.clone();
+                    let stream = stream.inspect(move |chunk| {
+                        if let Ok(chunk) = chunk {
+                            if let Some(usage) = &chunk.usage {
+                                let model = Some(chunk.model.as_str())
+                                    .filter(|m| !m.is_empty())
+                                    .unwrap_or(&model);
+                                let record = UsageRecord::new(
+                                    &provider_type,
+                                    model,
+                                    usage,
+                                    started.elapsed(),
+                                );
+                                if let Err(err) = ledger.record(record) {
+                                    warn!(error = %err, "Failed to record usage");
+                                }
+                            }
+                        }
+                    });
+                    return Ok(stream.boxed());
+                }
+                Err(err) if err.is_retryable() => {
+                    warn!(
+                        provider = %provider_type,
+                        error = %err,
+                        "Provider failed with a retryable error"
+                    );
+                    last_error = Some(err);
+                }
+                Err(err) => return Err(err),
+            }
+        }
+
+        Err(last_error.unwrap_or_else(|| GatewayError::Config("No provider available".to_string())))
     }
 
     /// Send a chat completion request with custom parameters
@@ -96,7 +278,7 @@ impl LLMGateway {
         max_tokens: Option<u32>,
         temperature: Option<f32>,
     ) -> Result<ChatResponse> {
-        let provider_type = provider_type.unwrap_or(self.config.default_provider);
+        let provider_type = provider_type.unwrap_or_else(|| self.config.default_provider.clone());
         let provider = self.get_provider(&provider_type)?;
 
         // Use specified model or provider's default model
@@ -117,7 +299,442 @@ impl LLMGateway {
             request = request.with_temperature(temp);
         }
 
-        provider.chat_completion(request).await
+        self.send(provider_type, request).await
+    }
+
+    /// Send a request and deserialize the JSON reply into `T`
+    ///
+    /// JSON mode is enabled when the request has no `response_format`. With a
+    /// JSON schema, the schema is also spelled out in the prompt and the reply
+    /// is validated against it. A reply that fails to parse or validate is
+    /// sent back to the model together with the error so it can correct it.
+    pub async fn chat_structured<T: DeserializeOwned>(
+        &self,
+        mut request: ChatRequest,
+        provider_type: Option<ProviderType>,
+    ) -> Result<T> {
+        let format = request
+            .response_format
+            .get_or_insert(ResponseFormat::JsonObject)
+            .clone();
+        if !format.is_json() {
+            return Err(GatewayError::InvalidRequest(
+                "Structured output requires a JSON response format".to_string(),
+            ));
+        }
+
+        let schema = format.schema().cloned();
+        let instruction = match &schema {
+            Some(schema) => format!(
+                "Respond with a single JSON object that conforms to this JSON schema:\n{}",
+                schema
+            ),
+            None => "Respond with a single JSON object.".to_string(),
+        };
+        let position = request
+            .messages
+            .iter()
+            .take_while(|message| message.role == MessageRole::System)
+            .count();
+        request
+            .messages
+            .insert(position, ChatMessage::system(instruction));
+
+        let provider_type = provider_type.unwrap_or_else(|| self.config.default_provider.clone());
+        let mut last_error = String::new();
+
+        for _ in 0..STRUCTURED_MAX_ATTEMPTS {
+            // An invalid reply is not cached, or the same attempt would be repeated
+            let response = self
+                .send_checked(provider_type.clone(), request.clone(), |response| {
+                    parse_structured::<T>(response.content().unwrap_or_default(), schema.as_ref())
+                        .is_ok()
+                })
+                .await?;
+            let content = response.content().unwrap_or_default().to_string();
+
+            match parse_structured(&content, schema.as_ref()) {
+                Ok(value) => return Ok(value),
+                Err(err) => {
+                    request.messages.push(ChatMessage::assistant(content));
+                    request.messages.push(ChatMessage::user(format!(
+                        "That reply was invalid: {}. Reply again with only the corrected JSON.",
+                        err
+                    )));
+                    last_error = err;
+                }
+            }
+        }
+
+        Err(GatewayError::InvalidResponse(format!(
+            "No valid JSON after {} attempts: {}",
+            STRUCTURED_MAX_ATTEMPTS, last_error
+        )))
+    }
+
+    /// Send a request, answering from the response cache when possible
+    ///
+    /// Streaming requests always go to the provider.
+    async fn send(
+        &self,
+        provider_type: ProviderType,
+        request: ChatRequest,
+    ) -> Result<ChatResponse> {
+        self.send_checked(provider_type, request, |_| true).await
+    }
+
+    /// Send a request like [`send`](Self::send), caching only responses that pass `check`
+    async fn send_checked(
+        &self,
+        provider_type: ProviderType,
+        request: ChatRequest,
+        check: impl Fn(&ChatResponse) -> bool,
+    ) -> Result<ChatResponse> {
+        let cache = self
+            .cache
+            .as_ref()
+            .filter(|_| self.cache_mode != CacheMode::Bypass && self.is_cacheable(&request));
+        let Some(cache) = cache else {
+            return self.send_uncached(provider_type, request).await;
+        };
+
+        let key = ResponseCache::key(&provider_type, &request)?;
+        if self.cache_mode == CacheMode::Use {
+            if let Some(response) = cache.get(&key) {
+                debug!(
+                    provider = %provider_type,
+                    model = %response.model,
+                    "Serving cached response"
+                );
+                return Ok(response);
+            }
+        }
+
+        let response = self.send_uncached(provider_type, request).await?;
+        if check(&response) {
+            // The cache is best effort: failing to write it never fails the call
+            if let Err(err) = cache.put(&key, &response) {
+                warn!(error = %err, "Failed to cache response");
+            }
+        }
+        Ok(response)
+    }
+
+    /// Whether a request may be answered from the cache
+    ///
+    /// Tool calls act on the caller's state, and a sampled request expects a
+    /// fresh sample unless sampled requests are cached on purpose.
+    fn is_cacheable(&self, request: &ChatRequest) -> bool {
+        let uses_tools = request
+            .tools
+            .as_ref()
+            .is_some_and(|tools| !tools.is_empty());
+        let sampled = request
+            .temperature
+            .is_some_and(|temperature| temperature > 0.0)
+            || request.n.is_some_and(|n| n > 1);
+        !uses_tools && (self.cache_sampled || !sampled)
+    }
+
+    /// Send a request along the fallback chain starting at `provider_type`
+    ///
+    /// Each provider is retried according to the retry policy; when it still
+    /// fails with a retryable error the next provider in the chain is tried.
+    async fn send_uncached(
+        &self,
+        provider_type: ProviderType,
+        request: ChatRequest,
+    ) -> Result<ChatResponse> {
+        let mut last_error = None;
+
+        for (index, (provider_type, provider)) in
+            self.fallback_chain(provider_type)?.into_iter().enumerate()
+        {
+            let request = Self::fit_budget(
+                &provider_type,
+                Self::fallback_request(index, &provider, &request),
+            )?;
+            let started = Instant::now();
+            let result = self.complete(&provider, request).await;
+
+            match result {
+                Ok(mut response) => {
+                    self.record_usage(&provider_type, &response, started);
+                    response.provider = Some(provider_type);
+                    return Ok(response);
+                }
+                Err(err) if err.is_retryable() => {
+                    warn!(
+                        provider = %provider_type,
+                        error = %err,
+                        "Provider failed with a retryable error"
+                    );
+                    last_error = Some(err);
+                }
+                Err(err) => return Err(err),
+            }
+        }
+
+        Err(last_error.unwrap_or_else(|| GatewayError::Config("No provider available".to_string())))
+    }
+
+    /// Call a provider, making extra calls when it returns fewer than `n` choices
+    ///
+    /// Providers without native `n` support get one call per choice; the
+    /// calls run in parallel and are merged into a single response.
+    async fn complete(
+        &self,
+        provider: &Arc<dyn Provider>,
+        mut request: ChatRequest,
+    ) -> Result<ChatResponse> {
+        let n = request.n.unwrap_or(1) as usize;
+        if !provider.supports_n() {
+            request.n = None;
+        }
+
+        let call = |request: ChatRequest| {
+            self.retry
+                .run(move || self.call_provider(provider, request.clone()))
+        };
+        if n <= 1 {
+            return call(request).await;
+        }
+
+        let mut response = call(request.clone()).await?;
+        if response.choices.len() >= n {
+            return Ok(response);
+        }
+
+        request.n = None;
+        let missing = n - response.choices.len();
+        let extra = future::try_join_all((0..missing).map(|_| call(request.clone()))).await?;
+        for other in extra {
+            response.merge(other);
+        }
+        Ok(response)
+    }
+
+    /// Call a provider once, running the middleware hooks around it
+    async fn call_provider(
+        &self,
+        provider: &Arc<dyn Provider>,
+        mut request: ChatRequest,
+    ) -> Result<ChatResponse> {
+        let context = RequestContext {
+            provider: provider.provider_type(),
+            stream: false,
+        };
+        let span = tracing::info_span!(
+            "chat_completion",
+            provider = %context.provider,
+            model = %request.model
+        );
+
+        async {
+            let started = Instant::now();
+            let result = async {
+                for middleware in &self.middleware {
+                    middleware.before_request(&context, &mut request).await?;
+                }
+                let mut response = provider.chat_completion(request).await?;
+                for middleware in self.middleware.iter().rev() {
+                    middleware.after_response(&context, &mut response).await?;
+                }
+                Ok(response)
+            }
+            .await;
+
+            let latency_ms = started.elapsed().as_millis() as u64;
+            match &result {
+                Ok(response) => info!(
+                    latency_ms,
+                    prompt_tokens = response.usage.prompt_tokens,
+                    completion_tokens = response.usage.completion_tokens,
+                    choices = response.choices.len(),
+                    "Completed"
+                ),
+                Err(err) => {
+                    warn!(latency_ms, error = %err, "Failed");
+                    self.notify_error(&context, err).await;
+                }
+            }
+            result
+        }
+        .instrument(span)
+        .await
+    }
+
+    /// Open a stream from a provider, running the request and error hooks
+    async fn call_provider_stream(
+        &self,
+        provider: &Arc<dyn Provider>,
+        mut request: ChatRequest,
+    ) -> Result<ChatStream> {
+        let context = RequestContext {
+            provider: provider.provider_type(),
+            stream: true,
+        };
+        let span = tracing::info_span!(
+            "chat_completion_stream",
+            provider = %context.provider,
+            model = %request.model
+        );
+
+        async {
+            let started = Instant::now();
+            let result = async {
+                for middleware in &self.middleware {
+                    middleware.before_request(&context, &mut request).await?;
+                }
+                provider.chat_completion_stream(request).await
+            }
+            .await;
+
+            let latency_ms = started.elapsed().as_millis() as u64;
+            match &result {
+                Ok(_) => info!(latency_ms, "Stream opened"),
+                Err(err) => {
+                    warn!(latency_ms, error = %err, "Failed");
+                    self.notify_error(&context, err).await;
+                }
+            }
+            result
+        }
+        .instrument(span)
+        .await
+    }
+
+    async fn notify_error(&self, context: &RequestContext, err: &GatewayError) {
+        for middleware in self.middleware.iter().rev() {
+            middleware.on_error(context, err).await;
+        }
+    }
+
+    /// Append a completed call to the usage ledger
+    ///
+    /// The ledger is best effort: failing to write it never fails the call.
+    fn record_usage(
+        &self,
+        provider_type: &ProviderType,
+        response: &ChatResponse,
+        started: Instant,
+    ) {
+        if let Some(ledger) = &self.ledger {
+            let record = UsageRecord::new(
+                provider_type,
+                &response.model,
+                &response.usage,
+                started.elapsed(),
+            );
+            if let Err(err) = ledger.record(record) {
+                warn!(error = %err, "Failed to record usage");
+            }
+        }
+    }
+
+    /// Providers to try for a request, starting with `provider_type`
+    fn fallback_chain(
+        &self,
+        provider_type: ProviderType,
+    ) -> Result<Vec<(ProviderType, Arc<dyn Provider>)>> {
+        let provider = self.get_provider(&provider_type)?;
+        let mut chain = vec![(provider_type, provider)];
+
+        for fallback in &self.config.fallback_providers {
+            if chain.iter().all(|(existing, _)| existing != fallback) {
+                if let Some(provider) = self.providers.get(fallback) {
+                    chain.push((fallback.clone(), provider.clone()));
+                }
+            }
+        }
+
+        Ok(chain)
+    }
+
+    /// Adapt a request for the provider at `index` in the fallback chain
+    ///
+    /// The requested model only applies to the primary provider; fallbacks
+    /// use their own default model.
+    fn fallback_request(
+        index: usize,
+        provider: &Arc<dyn Provider>,
+        request: &ChatRequest,
+    ) -> ChatRequest {
+        let mut request = request.clone();
+        if index > 0 {
+            if let Some(model) = provider.default_model() {
+                request.model = model.to_string();
+            }
+        }
+        request
+    }
+
+    /// Check a request against the model's context window before sending it
+    ///
+    /// `max_tokens` is lowered to what is left after the prompt; a prompt that
+    /// alone exceeds the context window is rejected.
+    fn fit_budget(provider_type: &ProviderType, mut request: ChatRequest) -> Result<ChatRequest> {
+        let budget = request.token_budget();
+        if budget.fits() {
+            return Ok(request);
+        }
+
+        match budget.available_completion_tokens() {
+            Some(available) if available > 0 => {
+                request.max_tokens = Some(available);
+                Ok(request)
+            }
+            _ => Err(GatewayError::ContextLengthExceeded(Box::new(ApiError {
+                provider: provider_type.to_string(),
+                status: StatusCode::BAD_REQUEST,
+                code: Some("context_length_exceeded".to_string()),
+                error_type: None,
+                message: format!(
+                    "Prompt of about {} tokens exceeds the {} token context window of {}",
+                    budget.prompt_tokens,
+                    budget.context_window.unwrap_or_default(),
+                    request.model
+                ),
+                request_id: None,
+                retry_after: None,
+                details: None,
+            }))),
+        }
+    }
+
+    /// List the models offered by every configured provider
+    ///
+    /// Providers are queried concurrently, each under the retry policy, and
+    /// returned sorted by name with their own result, so one failing provider
+    /// does not hide the others.
+    pub async fn list_models(&self) -> Vec<(ProviderType, Result<Vec<ModelInfo>>)> {
+        let mut providers: Vec<_> = self.providers.iter().collect();
+        providers.sort_by_key(|(provider_type, _)| provider_type.to_string());
+
+        future::join_all(providers.into_iter().map(|(provider_type, provider)| {
+            let span = tracing::info_span!("list_models", provider = %provider_type);
+            async move {
+                let mut models = self.retry.run(|| provider.list_models()).await;
+                match &mut models {
+                    Ok(models) => models.sort_by(|a, b| a.id.cmp(&b.id)),
+                    Err(err) => warn!(error = %err, "Failed to list models"),
+                }
+                (provider_type.clone(), models)
+            }
+            .instrument(span)
+        }))
+        .await
+    }
+
+    /// List the models offered by one provider
+    pub async fn list_models_with_provider(
+        &self,
+        provider_type: &ProviderType,
+    ) -> Result<Vec<ModelInfo>> {
+        let provider = self.get_provider(provider_type)?;
+        let mut models = self.retry.run(|| provider.list_models()).await?;
+        models.sort_by(|a, b| a.id.cmp(&b.id));
+        Ok(models)
     }
 
     /// Get a provider instance
@@ -129,12 +746,12 @@ impl LLMGateway {
 
     /// Get the list of available providers
     pub fn available_providers(&self) -> Vec<ProviderType> {
-        self.providers.keys().copied().collect()
+        self.providers.keys().cloned().collect()
     }
 
     /// Get the default provider type
     pub fn default_provider(&self) -> ProviderType {
-        self.config.default_provider
+        self.config.default_provider.clone()
     }
 
     /// Check if a provider is available
@@ -169,3 +786,25 @@ impl LLMGateway {
         Ok(response.content().unwrap_or("").to_string())
     }
 }
+
+/// Parse a JSON reply, tolerating a surrounding Markdown code fence
+fn parse_structured<T: DeserializeOwned>(
+    content: &str,
+    schema: Option<&serde_json::Value>,
+) -> std::result::Result<T, String> {
+    let content = content.trim();
+    let content = content
+        .strip_prefix("```json")
+        .or_else(|| content.strip_prefix("```"))
+        .and_then(|inner| inner.strip_suffix("```"))
+        .unwrap_or(content);
+
+    let value: serde_json::Value =
+        serde_json::from_str(content.trim()).map_err(|err| format!("not valid JSON ({})", err))?;
+
+    if let Some(schema) = schema {
+        schema::validate(schema, &value)?;
+    }
+
+    serde_json::from_value(value).map_err(|err| err.to_string())
+}
@@ -3,20 +3,37 @@
 //! A unified gateway library for multiple LLM providers with git commit message generation.
 
 // Core LLM Gateway modules
+pub mod cache;
+pub mod cassette;
 pub mod config;
+pub mod credentials;
 pub mod error;
 pub mod gateway;
+pub mod middleware;
 pub mod providers;
+mod redact;
+pub mod retry;
+pub mod schema;
+pub mod stream;
+pub mod tokenizer;
 pub mod types;
+pub mod usage;
 
 // Application modules
 pub mod commands;
 
 // Re-export main types for convenience
 pub use config::{GatewayConfig, ProviderConfig};
-pub use error::{GatewayError, Result};
+pub use credentials::{ApiKey, KeySource};
+pub use error::{ApiError, GatewayError, Result};
 pub use gateway::LLMGateway;
-pub use types::{ChatMessage, ChatRequest, ChatResponse, MessageRole};
+pub use middleware::{Middleware, RequestContext};
+pub use stream::ChatStream;
+pub use tokenizer::TokenBudget;
+pub use types::{
+    ChatCompletionChunk, ChatMessage, ChatRequest, ChatResponse, MessageRole, ModelInfo,
+    ResponseFormat, Tool, ToolCall, ToolChoice,
+};
 
 // Re-export provider types
 pub use providers::{Provider, ProviderType};
@@ -0,0 +1,47 @@
+//! Hooks that run around every provider call
+//!
+//! Middleware is registered on the gateway with
+//! [`LLMGateway::with_middleware`](crate::LLMGateway::with_middleware).
+//! `before_request` hooks run in registration order; `after_response` and
+//! `on_error` hooks run in reverse order, so the first middleware registered
+//! wraps all the others. Hooks run for every attempt, including retries and
+//! fallback providers, but not for responses served from the cache.
+
+use crate::error::{GatewayError, Result};
+use crate::providers::ProviderType;
+use crate::types::{ChatRequest, ChatResponse};
+use async_trait::async_trait;
+
+/// Information about the provider call a hook runs for
+#[derive(Debug, Clone)]
+pub struct RequestContext {
+    /// Provider the request is sent to
+    pub provider: ProviderType,
+    /// Whether the request is streamed
+    pub stream: bool,
+}
+
+/// Hooks to inspect or change requests and responses
+#[async_trait]
+pub trait Middleware: Send + Sync {
+    /// Called before the request is sent; an error aborts the call
+    async fn before_request(
+        &self,
+        _context: &RequestContext,
+        _request: &mut ChatRequest,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called with a successful response (not for streamed requests); an error fails the call
+    async fn after_response(
+        &self,
+        _context: &RequestContext,
+        _response: &mut ChatResponse,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called when the call fails, including failures from other hooks
+    async fn on_error(&self, _context: &RequestContext, _error: &GatewayError) {}
+}
@@ -0,0 +1,334 @@
+//! Anthropic Messages API provider implementation
+
+use crate::config::ProviderConfig;
+use crate::credentials::ApiKey;
+use crate::error::{GatewayError, Result};
+use crate::providers::{http, Provider, ProviderType};
+use crate::types::{
+    ChatChoice, ChatMessage, ChatRequest, ChatResponse, MessageRole, ResponseFormat, ToolCall,
+    ToolChoice, Usage,
+};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Anthropic API version sent when none is configured
+const DEFAULT_API_VERSION: &str = "2023-06-01";
+
+/// `max_tokens` is mandatory for the Messages API
+const DEFAULT_MAX_TOKENS: u32 = 4096;
+
+/// Anthropic provider
+pub struct AnthropicProvider {
+    client: http::HttpClient,
+    api_key: ApiKey,
+    base_url: String,
+    api_version: String,
+    default_model: Option<String>,
+}
+
+impl AnthropicProvider {
+    /// Create a new Anthropic provider
+    pub fn new(config: &ProviderConfig) -> Result<Self> {
+        // Like the official SDKs the base URL has no version; one ending in
+        // `/v1` is accepted too
+        let base_url = config
+            .base_url
+            .as_deref()
+            .unwrap_or("https://api.anthropic.com")
+            .trim_end_matches('/');
+        let base_url = base_url.strip_suffix("/v1").unwrap_or(base_url).to_string();
+
+        let api_version = config
+            .extra
+            .get("api_version")
+            .cloned()
+            .unwrap_or_else(|| DEFAULT_API_VERSION.to_string());
+
+        Ok(Self {
+            client: http::build_client(config)?,
+            api_key: config.api_key.clone(),
+            base_url,
+            api_version,
+            default_model: config.default_model.clone(),
+        })
+    }
+}
+
+/// Request body for the Messages API
+#[derive(Debug, Serialize)]
+struct MessagesRequest {
+    model: String,
+    max_tokens: u32,
+    messages: Vec<AnthropicMessage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tools: Vec<AnthropicTool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_choice: Option<Value>,
+    #[serde(flatten)]
+    extra: HashMap<String, Value>,
+}
+
+#[derive(Debug, Serialize)]
+struct AnthropicMessage {
+    role: &'static str,
+    content: Vec<RequestBlock>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum RequestBlock {
+    Text {
+        text: String,
+    },
+    ToolUse {
+        id: String,
+        name: String,
+        input: Value,
+    },
+    ToolResult {
+        tool_use_id: String,
+        content: String,
+    },
+}
+
+#[derive(Debug, Serialize)]
+struct AnthropicTool {
+    name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    input_schema: Value,
+}
+
+/// Response body from the Messages API
+#[derive(Debug, Deserialize)]
+struct MessagesResponse {
+    id: String,
+    model: String,
+    content: Vec<ContentBlock>,
+    stop_reason: Option<String>,
+    usage: AnthropicUsage,
+}
+
+#[derive(Debug, Deserialize)]
+struct ContentBlock {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    text: Option<String>,
+    #[serde(default)]
+    id: Option<String>,
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    input: Option<Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AnthropicUsage {
+    input_tokens: u32,
+    output_tokens: u32,
+}
+
+impl From<ChatRequest> for MessagesRequest {
+    fn from(request: ChatRequest) -> Self {
+        let mut system = Vec::new();
+        let mut messages: Vec<AnthropicMessage> = Vec::new();
+
+        for message in request.messages {
+            let (role, mut content) = match message.role {
+                MessageRole::System => {
+                    system.push(message.content);
+                    continue;
+                }
+                MessageRole::Assistant => ("assistant", text_blocks(message.content)),
+                MessageRole::User | MessageRole::Function => ("user", text_blocks(message.content)),
+                MessageRole::Tool => (
+                    "user",
+                    vec![RequestBlock::ToolResult {
+                        tool_use_id: message.tool_call_id.unwrap_or_default(),
+                        content: message.content,
+                    }],
+                ),
+            };
+
+            for call in message.tool_calls.unwrap_or_default() {
+                content.push(RequestBlock::ToolUse {
+                    id: call.id,
+                    name: call.function.name,
+                    input: serde_json::from_str(&call.function.arguments)
+                        .unwrap_or_else(|_| json!({})),
+                });
+            }
+
+            // Consecutive turns from the same role (e.g. several tool results) are merged
+            match messages.last_mut() {
+                Some(last) if last.role == role => last.content.append(&mut content),
+                _ => messages.push(AnthropicMessage { role, content }),
+            }
+        }
+
+        let tools = request
+            .tools
+            .unwrap_or_default()
+            .into_iter()
+            .map(|tool| AnthropicTool {
+                name: tool.function.name,
+                description: tool.function.description,
+                input_schema: tool.function.parameters,
+            })
+            .collect();
+
+        let mut tool_choice = request.tool_choice.map(|choice| match choice {
+            ToolChoice::Mode(mode) if mode == "required" => json!({ "type": "any" }),
+            ToolChoice::Mode(mode) => json!({ "type": mode }),
+            ToolChoice::Named { function, .. } => json!({ "type": "tool", "name": function.name }),
+        });
+        if request.parallel_tool_calls == Some(false) {
+            let choice = tool_choice.get_or_insert_with(|| json!({ "type": "auto" }));
+            if let Some(choice) = choice.as_object_mut() {
+                choice.insert("disable_parallel_tool_use".to_string(), json!(true));
+            }
+        }
+
+        // The Messages API has no JSON mode, so the format is requested in the prompt
+        match &request.response_format {
+            Some(ResponseFormat::JsonObject) => system
+                .push("Respond with a single JSON object and nothing else.".to_string()),
+            Some(ResponseFormat::JsonSchema { json_schema }) => system.push(format!(
+                "Respond with a single JSON object that conforms to this JSON schema and nothing else:\n{}",
+                json_schema.schema
+            )),
+            _ => {}
+        }
+
+        Self {
+            model: request.model,
+            max_tokens: request.max_tokens.unwrap_or(DEFAULT_MAX_TOKENS),
+            messages,
+            system: (!system.is_empty()).then(|| system.join("\n\n")),
+            temperature: request.temperature,
+            top_p: request.top_p,
+            tools,
+            tool_choice,
+            extra: request.extra,
+        }
+    }
+}
+
+/// Text content as blocks; the API rejects empty text blocks
+fn text_blocks(text: String) -> Vec<RequestBlock> {
+    if text.is_empty() {
+        Vec::new()
+    } else {
+        vec![RequestBlock::Text { text }]
+    }
+}
+
+impl From<MessagesResponse> for ChatResponse {
+    fn from(response: MessagesResponse) -> Self {
+        let mut content = String::new();
+        let mut tool_calls = Vec::new();
+
+        for block in response.content {
+            match block.kind.as_str() {
+                "text" => content.push_str(block.text.as_deref().unwrap_or_default()),
+                "tool_use" => tool_calls.push(ToolCall::function(
+                    block.id.unwrap_or_default(),
+                    block.name.unwrap_or_default(),
+                    block.input.unwrap_or_else(|| json!({})).to_string(),
+                )),
+                _ => {}
+            }
+        }
+
+        let mut message = ChatMessage::assistant(content);
+        if !tool_calls.is_empty() {
+            message.tool_calls = Some(tool_calls);
+        }
+
+        let finish_reason = response.stop_reason.map(|reason| {
+            match reason.as_str() {
+                "end_turn" | "stop_sequence" => "stop",
+                "max_tokens" => "length",
+                "tool_use" => "tool_calls",
+                other => other,
+            }
+            .to_string()
+        });
+
+        let created = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+
+        ChatResponse {
+            id: response.id,
+            object: "chat.completion".to_string(),
+            created,
+            model: response.model,
+            choices: vec![ChatChoice {
+                index: 0,
+                message,
+                finish_reason,
+            }],
+            usage: Usage {
+                prompt_tokens: response.usage.input_tokens,
+                completion_tokens: response.usage.output_tokens,
+                total_tokens: response.usage.input_tokens + response.usage.output_tokens,
+            },
+            system_fingerprint: None,
+            provider: None,
+            cached: false,
+        }
+    }
+}
+
+#[async_trait]
+impl Provider for AnthropicProvider {
+    fn provider_type(&self) -> ProviderType {
+        ProviderType::Anthropic
+    }
+
+    async fn chat_completion(&self, mut request: ChatRequest) -> Result<ChatResponse> {
+        let url = format!("{}/v1/messages", self.base_url);
+
+        // Streaming is not supported by this provider
+        request.stream = None;
+        let body = MessagesRequest::from(request);
+
+        let request = self
+            .client
+            .post(&url)
+            .header("x-api-key", self.api_key.resolve().await?)
+            .header("anthropic-version", &self.api_version)
+            .header("Content-Type", "application/json")
+            .json(&body);
+
+        let response: MessagesResponse = self.client.send_json("Anthropic", request).await?;
+        Ok(response.into())
+    }
+
+    fn default_model(&self) -> Option<&str> {
+        self.default_model
+            .as_deref()
+            .or(Some("claude-3-5-sonnet-latest"))
+    }
+
+    fn validate_config(&self) -> Result<()> {
+        if self.api_key.is_empty() {
+            return Err(GatewayError::Config(
+                "Anthropic API key is required".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
@@ -1,16 +1,18 @@
 //! Azure OpenAI provider implementation
 
 use crate::config::ProviderConfig;
+use crate::credentials::ApiKey;
 use crate::error::{GatewayError, Result};
-use crate::providers::{Provider, ProviderType};
-use crate::types::{ChatRequest, ChatResponse};
+use crate::providers::{http, Provider, ProviderType};
+use crate::stream::{sse_stream, ChatStream};
+use crate::types::{ChatRequest, ChatResponse, ModelInfo};
 use async_trait::async_trait;
-use reqwest::Client;
+use serde_json::json;
 
 /// Azure OpenAI provider
 pub struct AzureProvider {
-    client: Client,
-    api_key: String,
+    client: http::HttpClient,
+    api_key: ApiKey,
     endpoint: String,
     api_version: String,
     deployment_name: Option<String>,
@@ -31,13 +33,41 @@ impl AzureProvider {
             .unwrap_or_else(|| "2024-02-15-preview".to_string());
 
         Ok(Self {
-            client: Client::new(),
+            client: http::build_client(config)?,
             api_key: config.api_key.clone(),
             endpoint,
             api_version,
             deployment_name: config.default_model.clone(),
         })
     }
+
+    /// Chat completions URL of the deployment named by the request's model
+    ///
+    /// Azure routes by deployment, so the model name is the deployment name;
+    /// an empty model uses the configured deployment.
+    fn chat_url(&self, request: &ChatRequest) -> Result<String> {
+        let deployment = Some(request.model.as_str())
+            .filter(|model| !model.is_empty())
+            .or(self.deployment_name.as_deref())
+            .ok_or_else(|| GatewayError::Config("Azure deployment name is required".to_string()))?;
+
+        // The name goes into the URL path, so it must not be able to change it
+        let valid = deployment.starts_with(|c: char| c.is_ascii_alphanumeric())
+            && deployment
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'));
+        if !valid {
+            return Err(GatewayError::InvalidRequest(format!(
+                "Invalid Azure deployment name {:?}",
+                deployment
+            )));
+        }
+
+        Ok(format!(
+            "{}/openai/deployments/{}/chat/completions?api-version={}",
+            self.endpoint, deployment, self.api_version
+        ))
+    }
 }
 
 #[async_trait]
@@ -46,40 +76,51 @@ impl Provider for AzureProvider {
         ProviderType::Azure
     }
 
-    async fn chat_completion(&self, mut request: ChatRequest) -> Result<ChatResponse> {
-        let deployment = self
-            .deployment_name
-            .as_ref()
-            .ok_or_else(|| GatewayError::Config("Azure deployment name is required".to_string()))?;
+    async fn chat_completion(&self, request: ChatRequest) -> Result<ChatResponse> {
+        let url = self.chat_url(&request)?;
 
-        let url = format!(
-            "{}/openai/deployments/{}/chat/completions?api-version={}",
-            self.endpoint, deployment, self.api_version
-        );
+        let request = self
+            .client
+            .post(&url)
+            .header("api-key", self.api_key.resolve().await?)
+            .header("Content-Type", "application/json")
+            .json(&request);
+
+        self.client.send_json("Azure OpenAI", request).await
+    }
 
-        // Azure uses deployment name instead of model
-        request.model = deployment.clone();
+    async fn chat_completion_stream(&self, mut request: ChatRequest) -> Result<ChatStream> {
+        let url = self.chat_url(&request)?;
 
-        let response = self
+        request.stream = Some(true);
+        request.extra.insert(
+            "stream_options".to_string(),
+            json!({ "include_usage": true }),
+        );
+
+        let request = self
             .client
             .post(&url)
-            .header("api-key", &self.api_key)
+            .header("api-key", self.api_key.resolve().await?)
             .header("Content-Type", "application/json")
-            .json(&request)
-            .send()
-            .await?;
-
-        if !response.status().is_success() {
-            let status = response.status();
-            let error_text = response.text().await.unwrap_or_default();
-            return Err(GatewayError::Provider(format!(
-                "Azure OpenAI API error {}: {}",
-                status, error_text
-            )));
-        }
+            .header("Accept", "text/event-stream")
+            .json(&request);
 
-        let chat_response: ChatResponse = response.json().await?;
-        Ok(chat_response)
+        let response = self.client.send("Azure OpenAI", request).await?;
+
+        Ok(sse_stream("Azure OpenAI", response))
+    }
+
+    /// List the configured deployment
+    ///
+    /// Listing all deployments needs the Azure management API rather than
+    /// the resource's API key.
+    async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        Ok(self
+            .deployment_name
+            .iter()
+            .map(|deployment| ModelInfo::new(deployment.clone()))
+            .collect())
     }
 
     fn default_model(&self) -> Option<&str> {
@@ -90,6 +131,10 @@ impl Provider for AzureProvider {
         true
     }
 
+    fn supports_n(&self) -> bool {
+        true
+    }
+
     fn validate_config(&self) -> Result<()> {
         if self.api_key.is_empty() {
             return Err(GatewayError::Config(
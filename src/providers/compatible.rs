@@ -0,0 +1,234 @@
+//! OpenAI-compatible provider implementation
+//!
+//! Covers any endpoint that speaks the OpenAI chat completions protocol.
+//! OpenAI, DeepSeek and Qwen are built-in [`Preset`]s; other endpoints
+//! (Moonshot, Groq, SiliconFlow, vLLM, internal gateways, ...) are defined
+//! by name in the configuration.
+
+use crate::config::{AuthStyle, ProviderConfig};
+use crate::credentials::ApiKey;
+use crate::error::{GatewayError, Result};
+use crate::providers::{http, json_object_only, ModelList, Provider, ProviderType};
+use crate::stream::{sse_stream, ChatStream};
+use crate::types::{ChatRequest, ChatResponse, ModelInfo};
+use async_trait::async_trait;
+use reqwest::RequestBuilder;
+use serde_json::json;
+
+/// Defaults of a built-in OpenAI-compatible provider
+#[derive(Debug, Clone)]
+pub struct Preset {
+    pub provider_type: ProviderType,
+    /// Name used in errors and logs
+    pub name: &'static str,
+    /// Base URL used unless the configuration sets one
+    pub base_url: &'static str,
+    /// Model used unless the configuration sets one
+    pub default_model: &'static str,
+    pub auth: AuthStyle,
+    /// Whether `json_schema` response formats are accepted, rather than only `json_object`
+    pub json_schema: bool,
+    /// Whether several choices can be requested with `n`
+    pub supports_n: bool,
+}
+
+/// OpenAI
+pub const OPENAI: Preset = Preset {
+    provider_type: ProviderType::OpenAI,
+    name: "OpenAI",
+    base_url: "https://api.openai.com/v1",
+    default_model: "gpt-3.5-turbo",
+    auth: AuthStyle::Bearer,
+    json_schema: true,
+    supports_n: true,
+};
+
+/// DeepSeek
+pub const DEEPSEEK: Preset = Preset {
+    provider_type: ProviderType::DeepSeek,
+    name: "DeepSeek",
+    base_url: "https://api.deepseek.com",
+    default_model: "deepseek-chat",
+    auth: AuthStyle::Bearer,
+    json_schema: false,
+    supports_n: false,
+};
+
+/// Qwen through DashScope's compatible mode
+pub const QWEN: Preset = Preset {
+    provider_type: ProviderType::Qwen,
+    name: "Qwen",
+    base_url: "https://dashscope.aliyuncs.com/compatible-mode/v1",
+    default_model: "qwen-max",
+    auth: AuthStyle::Bearer,
+    json_schema: false,
+    supports_n: false,
+};
+
+/// OpenAI-compatible provider
+pub struct CompatibleProvider {
+    client: http::HttpClient,
+    provider_type: ProviderType,
+    name: String,
+    api_key: ApiKey,
+    base_url: String,
+    default_model: Option<String>,
+    auth: AuthStyle,
+    json_schema: bool,
+    supports_n: bool,
+}
+
+impl CompatibleProvider {
+    /// Create a new user-defined OpenAI-compatible provider named `name`
+    ///
+    /// `n` and `json_schema` response formats are only sent when the
+    /// `supports_n` and `json_schema` extra settings are `true`.
+    pub fn new(name: &str, config: &ProviderConfig) -> Result<Self> {
+        let base_url = config.base_url.clone().ok_or_else(|| {
+            GatewayError::Config(format!("Base URL is required for provider {}", name))
+        })?;
+
+        Ok(Self {
+            client: http::build_client(config)?,
+            provider_type: ProviderType::Custom(name.to_string()),
+            name: name.to_string(),
+            api_key: config.api_key.clone(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            default_model: config.default_model.clone(),
+            auth: config.auth.clone(),
+            json_schema: flag(name, config, "json_schema")?,
+            supports_n: flag(name, config, "supports_n")?,
+        })
+    }
+
+    /// Create a built-in provider from its preset
+    pub fn with_preset(preset: &Preset, config: &ProviderConfig) -> Result<Self> {
+        let base_url = config.base_url.as_deref().unwrap_or(preset.base_url);
+
+        Ok(Self {
+            client: http::build_client(config)?,
+            provider_type: preset.provider_type.clone(),
+            name: preset.name.to_string(),
+            api_key: config.api_key.clone(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            default_model: Some(
+                config
+                    .default_model
+                    .clone()
+                    .unwrap_or_else(|| preset.default_model.to_string()),
+            ),
+            auth: preset.auth.clone(),
+            json_schema: preset.json_schema,
+            supports_n: preset.supports_n,
+        })
+    }
+
+    /// Build a POST request with authentication applied
+    async fn post(&self, url: &str) -> Result<RequestBuilder> {
+        self.authorize(
+            self.client
+                .post(url)
+                .header("Content-Type", "application/json"),
+        )
+        .await
+    }
+
+    /// Apply the configured authentication to a request
+    async fn authorize(&self, request: RequestBuilder) -> Result<RequestBuilder> {
+        let api_key = self.api_key.resolve().await?;
+        Ok(match &self.auth {
+            AuthStyle::Bearer => request.header("Authorization", format!("Bearer {}", api_key)),
+            AuthStyle::ApiKey => request.header("api-key", api_key),
+            AuthStyle::Header(name) => request.header(name.as_str(), api_key),
+            AuthStyle::None => request,
+        })
+    }
+}
+
+/// Read a boolean extra setting, `false` unless set
+fn flag(name: &str, config: &ProviderConfig, key: &str) -> Result<bool> {
+    match config.extra.get(key) {
+        Some(value) => value.parse().map_err(|_| {
+            GatewayError::Config(format!("Invalid {} for provider {}: {}", key, name, value))
+        }),
+        None => Ok(false),
+    }
+}
+
+#[async_trait]
+impl Provider for CompatibleProvider {
+    fn provider_type(&self) -> ProviderType {
+        self.provider_type.clone()
+    }
+
+    async fn chat_completion(&self, mut request: ChatRequest) -> Result<ChatResponse> {
+        let url = format!("{}/chat/completions", self.base_url);
+
+        if !self.json_schema {
+            json_object_only(&mut request);
+        }
+
+        let request = self.post(&url).await?.json(&request);
+
+        self.client.send_json(&self.name, request).await
+    }
+
+    async fn chat_completion_stream(&self, mut request: ChatRequest) -> Result<ChatStream> {
+        let url = format!("{}/chat/completions", self.base_url);
+
+        if !self.json_schema {
+            json_object_only(&mut request);
+        }
+        request.stream = Some(true);
+        request.extra.insert(
+            "stream_options".to_string(),
+            json!({ "include_usage": true }),
+        );
+
+        let request = self
+            .post(&url)
+            .await?
+            .header("Accept", "text/event-stream")
+            .json(&request);
+
+        let response = self.client.send(&self.name, request).await?;
+
+        Ok(sse_stream(&self.name, response))
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        let url = format!("{}/models", self.base_url);
+
+        let request = self.authorize(self.client.get(&url)).await?;
+        let list: ModelList = self.client.send_json(&self.name, request).await?;
+        Ok(list.data)
+    }
+
+    fn default_model(&self) -> Option<&str> {
+        self.default_model.as_deref()
+    }
+
+    fn supports_streaming(&self) -> bool {
+        true
+    }
+
+    fn supports_n(&self) -> bool {
+        self.supports_n
+    }
+
+    fn validate_config(&self) -> Result<()> {
+        if self.auth != AuthStyle::None && self.api_key.is_empty() {
+            return Err(GatewayError::Config(format!(
+                "API key is required for provider {}",
+                self.name
+            )));
+        }
+        if self.base_url.is_empty() {
+            return Err(GatewayError::Config(format!(
+                "Base URL is required for provider {}",
+                self.name
+            )));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,460 @@
+//! Google Gemini provider implementation
+
+use crate::config::ProviderConfig;
+use crate::credentials::ApiKey;
+use crate::error::{GatewayError, Result};
+use crate::providers::{http, Provider, ProviderType};
+use crate::types::{
+    ChatChoice, ChatMessage, ChatRequest, ChatResponse, MessageRole, ResponseFormat, ToolCall,
+    ToolChoice, Usage,
+};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::debug;
+
+/// Extra request parameters and the `generationConfig` fields they map to
+const GENERATION_CONFIG_PARAMS: &[(&str, &str)] = &[
+    ("stop", "stopSequences"),
+    ("presence_penalty", "presencePenalty"),
+    ("frequency_penalty", "frequencyPenalty"),
+    ("seed", "seed"),
+    ("top_k", "topK"),
+    ("logprobs", "responseLogprobs"),
+    ("top_logprobs", "logprobs"),
+];
+
+/// Top-level `generateContent` fields that extra parameters may set directly
+const REQUEST_FIELDS: &[&str] = &["safetySettings", "cachedContent", "labels"];
+
+/// Gemini provider
+pub struct GeminiProvider {
+    client: http::HttpClient,
+    api_key: ApiKey,
+    base_url: String,
+    default_model: Option<String>,
+}
+
+impl GeminiProvider {
+    /// Create a new Gemini provider
+    pub fn new(config: &ProviderConfig) -> Result<Self> {
+        let base_url = config
+            .base_url
+            .clone()
+            .unwrap_or_else(|| "https://generativelanguage.googleapis.com/v1beta".to_string());
+
+        Ok(Self {
+            client: http::build_client(config)?,
+            api_key: config.api_key.clone(),
+            base_url,
+            default_model: config.default_model.clone(),
+        })
+    }
+}
+
+/// Request body for `generateContent`
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GenerateContentRequest {
+    contents: Vec<Content>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system_instruction: Option<Content>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    generation_config: Option<GenerationConfig>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tools: Vec<GeminiTool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_config: Option<Value>,
+    #[serde(flatten)]
+    extra: HashMap<String, Value>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiTool {
+    function_declarations: Vec<FunctionDeclaration>,
+}
+
+#[derive(Debug, Serialize)]
+struct FunctionDeclaration {
+    name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    parameters: Value,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Content {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    role: Option<String>,
+    #[serde(default)]
+    parts: Vec<Part>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Part {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    text: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    function_call: Option<GeminiFunctionCall>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    function_response: Option<FunctionResponse>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct GeminiFunctionCall {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    id: Option<String>,
+    name: String,
+    #[serde(default)]
+    args: Value,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct FunctionResponse {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    id: Option<String>,
+    name: String,
+    response: Value,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GenerationConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_output_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    candidate_count: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_mime_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_json_schema: Option<Value>,
+    #[serde(flatten)]
+    extra: HashMap<String, Value>,
+}
+
+/// Response body from `generateContent`
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GenerateContentResponse {
+    #[serde(default)]
+    candidates: Vec<Candidate>,
+    #[serde(default)]
+    usage_metadata: Option<UsageMetadata>,
+    #[serde(default)]
+    model_version: Option<String>,
+    #[serde(default)]
+    response_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Candidate {
+    #[serde(default)]
+    content: Option<Content>,
+    #[serde(default)]
+    finish_reason: Option<String>,
+    #[serde(default)]
+    index: Option<u32>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UsageMetadata {
+    #[serde(default)]
+    prompt_token_count: u32,
+    #[serde(default)]
+    candidates_token_count: u32,
+    #[serde(default)]
+    total_token_count: u32,
+}
+
+impl Content {
+    fn text(role: Option<&str>, text: String) -> Self {
+        Self {
+            role: role.map(str::to_string),
+            parts: vec![Part {
+                text: Some(text),
+                ..Part::default()
+            }],
+        }
+    }
+}
+
+impl From<ChatRequest> for GenerateContentRequest {
+    fn from(request: ChatRequest) -> Self {
+        let mut system = Vec::new();
+        let mut contents: Vec<Content> = Vec::new();
+        // Function responses must name the function; remember it by call ID
+        let mut call_names = HashMap::new();
+
+        for message in request.messages {
+            let content = match message.role {
+                MessageRole::System => {
+                    system.push(message.content);
+                    continue;
+                }
+                MessageRole::Assistant => {
+                    let mut content = Content::text(Some("model"), message.content);
+                    content
+                        .parts
+                        .retain(|part| part.text.as_deref() != Some(""));
+                    for call in message.tool_calls.unwrap_or_default() {
+                        call_names.insert(call.id.clone(), call.function.name.clone());
+                        content.parts.push(Part {
+                            function_call: Some(GeminiFunctionCall {
+                                id: Some(call.id),
+                                name: call.function.name,
+                                args: serde_json::from_str(&call.function.arguments)
+                                    .unwrap_or_else(|_| json!({})),
+                            }),
+                            ..Part::default()
+                        });
+                    }
+                    content
+                }
+                MessageRole::User | MessageRole::Function => {
+                    Content::text(Some("user"), message.content)
+                }
+                MessageRole::Tool => {
+                    let name = message
+                        .name
+                        .or_else(|| {
+                            message
+                                .tool_call_id
+                                .as_ref()
+                                .and_then(|id| call_names.get(id).cloned())
+                        })
+                        .unwrap_or_default();
+                    let response = match serde_json::from_str::<Value>(&message.content) {
+                        Ok(value @ Value::Object(_)) => value,
+                        _ => json!({ "content": message.content }),
+                    };
+                    Content {
+                        role: Some("user".to_string()),
+                        parts: vec![Part {
+                            function_response: Some(FunctionResponse {
+                                id: message.tool_call_id,
+                                name,
+                                response,
+                            }),
+                            ..Part::default()
+                        }],
+                    }
+                }
+            };
+
+            // Consecutive turns from the same role (e.g. several tool results) are merged
+            match contents.last_mut() {
+                Some(last) if last.role == content.role => last.parts.extend(content.parts),
+                _ => contents.push(content),
+            }
+        }
+
+        let declarations: Vec<_> = request
+            .tools
+            .unwrap_or_default()
+            .into_iter()
+            .map(|tool| FunctionDeclaration {
+                name: tool.function.name,
+                description: tool.function.description,
+                parameters: tool.function.parameters,
+            })
+            .collect();
+        let tools = if declarations.is_empty() {
+            Vec::new()
+        } else {
+            vec![GeminiTool {
+                function_declarations: declarations,
+            }]
+        };
+
+        let tool_config = request.tool_choice.map(|choice| {
+            let config = match choice {
+                ToolChoice::Mode(mode) if mode == "required" => json!({ "mode": "ANY" }),
+                ToolChoice::Mode(mode) => json!({ "mode": mode.to_uppercase() }),
+                ToolChoice::Named { function, .. } => {
+                    json!({ "mode": "ANY", "allowedFunctionNames": [function.name] })
+                }
+            };
+            json!({ "functionCallingConfig": config })
+        });
+
+        let json = request
+            .response_format
+            .as_ref()
+            .is_some_and(ResponseFormat::is_json);
+        let response_json_schema = request
+            .response_format
+            .as_ref()
+            .and_then(ResponseFormat::schema)
+            .cloned();
+
+        // OpenAI-style parameters belong in generationConfig; unknown ones are dropped
+        // rather than sent as top-level fields Gemini rejects
+        let mut generation_extra = HashMap::new();
+        let mut extra = HashMap::new();
+        for (key, value) in request.extra {
+            if let Some((_, field)) = GENERATION_CONFIG_PARAMS
+                .iter()
+                .find(|(param, _)| *param == key)
+            {
+                let value = match value {
+                    Value::String(stop) if key == "stop" => json!([stop]),
+                    value => value,
+                };
+                generation_extra.insert(field.to_string(), value);
+            } else if REQUEST_FIELDS.contains(&key.as_str()) {
+                extra.insert(key, value);
+            } else {
+                debug!(parameter = %key, "Dropping parameter Gemini does not support");
+            }
+        }
+
+        let generation_config = (request.temperature.is_some()
+            || request.top_p.is_some()
+            || request.max_tokens.is_some()
+            || request.n.is_some()
+            || json
+            || !generation_extra.is_empty())
+        .then_some(GenerationConfig {
+            temperature: request.temperature,
+            top_p: request.top_p,
+            max_output_tokens: request.max_tokens,
+            candidate_count: request.n,
+            response_mime_type: json.then(|| "application/json".to_string()),
+            response_json_schema,
+            extra: generation_extra,
+        });
+
+        Self {
+            contents,
+            system_instruction: (!system.is_empty())
+                .then(|| Content::text(None, system.join("\n\n"))),
+            generation_config,
+            tools,
+            tool_config,
+            extra,
+        }
+    }
+}
+
+impl GenerateContentResponse {
+    fn into_chat_response(self, model: String) -> ChatResponse {
+        let choices = self
+            .candidates
+            .into_iter()
+            .enumerate()
+            .map(|(i, candidate)| {
+                let mut content = String::new();
+                let mut tool_calls = Vec::new();
+
+                for part in candidate.content.map(|c| c.parts).unwrap_or_default() {
+                    if let Some(text) = part.text {
+                        content.push_str(&text);
+                    }
+                    if let Some(call) = part.function_call {
+                        let id = call
+                            .id
+                            .unwrap_or_else(|| format!("call_{}_{}", i, tool_calls.len()));
+                        tool_calls.push(ToolCall::function(id, call.name, call.args.to_string()));
+                    }
+                }
+
+                let finish_reason = candidate.finish_reason.map(|reason| match reason.as_str() {
+                    "STOP" => "stop".to_string(),
+                    "MAX_TOKENS" => "length".to_string(),
+                    "SAFETY" | "RECITATION" | "BLOCKLIST" | "PROHIBITED_CONTENT" => {
+                        "content_filter".to_string()
+                    }
+                    other => other.to_lowercase(),
+                });
+
+                let mut message = ChatMessage::assistant(content);
+                let finish_reason = if tool_calls.is_empty() {
+                    finish_reason
+                } else {
+                    message.tool_calls = Some(tool_calls);
+                    Some("tool_calls".to_string())
+                };
+
+                ChatChoice {
+                    index: candidate.index.unwrap_or(i as u32),
+                    message,
+                    finish_reason,
+                }
+            })
+            .collect();
+
+        let usage = self.usage_metadata.unwrap_or_default();
+
+        let created = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+
+        ChatResponse {
+            id: self.response_id.unwrap_or_default(),
+            object: "chat.completion".to_string(),
+            created,
+            model: self.model_version.unwrap_or(model),
+            choices,
+            usage: Usage {
+                prompt_tokens: usage.prompt_token_count,
+                completion_tokens: usage.candidates_token_count,
+                total_tokens: usage.total_token_count,
+            },
+            system_fingerprint: None,
+            provider: None,
+            cached: false,
+        }
+    }
+}
+
+#[async_trait]
+impl Provider for GeminiProvider {
+    fn provider_type(&self) -> ProviderType {
+        ProviderType::Gemini
+    }
+
+    async fn chat_completion(&self, request: ChatRequest) -> Result<ChatResponse> {
+        let url = format!("{}/models/{}:generateContent", self.base_url, request.model);
+
+        let model = request.model.clone();
+        let body = GenerateContentRequest::from(request);
+
+        let request = self
+            .client
+            .post(&url)
+            .header("x-goog-api-key", self.api_key.resolve().await?)
+            .header("Content-Type", "application/json")
+            .json(&body);
+
+        let response: GenerateContentResponse = self.client.send_json("Gemini", request).await?;
+        Ok(response.into_chat_response(model))
+    }
+
+    fn default_model(&self) -> Option<&str> {
+        self.default_model.as_deref().or(Some("gemini-2.0-flash"))
+    }
+
+    fn supports_n(&self) -> bool {
+        true
+    }
+
+    fn validate_config(&self) -> Result<()> {
+        if self.api_key.is_empty() {
+            return Err(GatewayError::Config(
+                "Gemini API key is required".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
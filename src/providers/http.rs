@@ -0,0 +1,424 @@
+//! Shared HTTP plumbing for provider implementations
+
+use crate::cassette::Cassette;
+use crate::config::ProviderConfig;
+use crate::error::{ApiError, GatewayError, Result};
+use crate::redact;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::{
+    Certificate, Client, Identity, NoProxy, Proxy, RequestBuilder, Response, StatusCode,
+};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+use tracing::{debug, trace, Level};
+
+/// Connect timeout used when none is configured
+const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Idle read timeout used when none is configured
+///
+/// Generous because slow local models and reasoning models can take minutes
+/// before the first byte, but finite so that a stalled server cannot hang a call.
+const DEFAULT_READ_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// User-Agent sent unless configured otherwise
+const DEFAULT_USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+
+/// HTTP client of a provider, going through a cassette when one is configured
+#[derive(Clone)]
+pub(crate) struct HttpClient {
+    client: Client,
+    cassette: Option<Arc<Cassette>>,
+}
+
+/// Build an HTTP client with the timeouts, headers, proxy, TLS and cassette settings of a provider
+pub(crate) fn build_client(config: &ProviderConfig) -> Result<HttpClient> {
+    let cassette = config.cassette.as_ref().map(Cassette::open).transpose()?;
+    Ok(HttpClient {
+        client: build_reqwest_client(config)?,
+        cassette,
+    })
+}
+
+/// Build the underlying client with the timeouts, headers, proxy and TLS settings of a provider
+fn build_reqwest_client(config: &ProviderConfig) -> Result<Client> {
+    let mut builder = Client::builder()
+        .connect_timeout(
+            config
+                .connect_timeout_seconds
+                .map(Duration::from_secs)
+                .unwrap_or(DEFAULT_CONNECT_TIMEOUT),
+        )
+        .read_timeout(
+            config
+                .read_timeout_seconds
+                .map(Duration::from_secs)
+                .unwrap_or(DEFAULT_READ_TIMEOUT),
+        );
+
+    if let Some(timeout) = config.timeout_seconds {
+        builder = builder.timeout(Duration::from_secs(timeout));
+    }
+
+    let network = &config.network;
+    builder = builder
+        .user_agent(network.user_agent.as_deref().unwrap_or(DEFAULT_USER_AGENT))
+        .default_headers(header_map(&config.headers)?);
+
+    // Without an explicit proxy reqwest uses HTTP_PROXY, HTTPS_PROXY and NO_PROXY
+    if let Some(url) = &network.proxy {
+        // The URL may contain credentials, so it is left out of the error
+        let proxy = Proxy::all(url)
+            .map_err(|_| GatewayError::Config("Invalid proxy URL".to_string()))?
+            .no_proxy(network.no_proxy.as_deref().and_then(NoProxy::from_string));
+        builder = builder.proxy(proxy);
+    }
+
+    for path in &network.ca_certs {
+        let pem = read_file(path, "CA certificate")?;
+        let certs = Certificate::from_pem_bundle(&pem).map_err(|err| {
+            GatewayError::Config(format!(
+                "Invalid CA certificate {}: {}",
+                path.display(),
+                err
+            ))
+        })?;
+        for cert in certs {
+            builder = builder.add_root_certificate(cert);
+        }
+    }
+
+    match (&network.client_cert, &network.client_key) {
+        (Some(cert), Some(key)) => {
+            let identity = Identity::from_pkcs8_pem(
+                &read_file(cert, "client certificate")?,
+                &read_file(key, "client key")?,
+            )
+            .map_err(|err| {
+                GatewayError::Config(format!("Invalid client certificate or key: {}", err))
+            })?;
+            builder = builder.identity(identity);
+        }
+        (None, None) => {}
+        _ => {
+            return Err(GatewayError::Config(
+                "client_cert and client_key must be set together".to_string(),
+            ))
+        }
+    }
+
+    builder
+        .build()
+        .map_err(|err| GatewayError::Config(format!("Failed to build HTTP client: {}", err)))
+}
+
+/// Convert configured headers into a header map
+fn header_map(headers: &HashMap<String, String>) -> Result<HeaderMap> {
+    let mut map = HeaderMap::new();
+    for (name, value) in headers {
+        let name = HeaderName::from_bytes(name.as_bytes())
+            .map_err(|_| GatewayError::Config(format!("Invalid header name: {}", name)))?;
+        let value = HeaderValue::from_str(value)
+            .map_err(|_| GatewayError::Config(format!("Invalid value for header {}", name)))?;
+        map.insert(name, value);
+    }
+    Ok(map)
+}
+
+/// Read a certificate or key file
+fn read_file(path: &Path, what: &str) -> Result<Vec<u8>> {
+    fs::read(path).map_err(|err| {
+        GatewayError::Config(format!(
+            "Failed to read {} {}: {}",
+            what,
+            path.display(),
+            err
+        ))
+    })
+}
+
+impl HttpClient {
+    /// Start a GET request
+    pub(crate) fn get(&self, url: &str) -> RequestBuilder {
+        self.client.get(url)
+    }
+
+    /// Start a POST request
+    pub(crate) fn post(&self, url: &str) -> RequestBuilder {
+        self.client.post(url)
+    }
+
+    /// Send a request and return the response if its status is successful
+    pub(crate) async fn send(&self, provider: &str, request: RequestBuilder) -> Result<Response> {
+        let (client, request) = request.build_split();
+        let request = request?;
+        let method = request.method().clone();
+        let url = redact::url(request.url());
+        if tracing::enabled!(Level::TRACE) {
+            let body = request
+                .body()
+                .and_then(|body| body.as_bytes())
+                .map(|body| redact::body(&String::from_utf8_lossy(body)))
+                .unwrap_or_default();
+            trace!(
+                provider,
+                %method,
+                %url,
+                headers = ?redact::headers(request.headers()),
+                %body,
+                "Sending request"
+            );
+        }
+
+        let started = Instant::now();
+        let result = match &self.cassette {
+            Some(cassette) => cassette.execute(provider, &client, request).await,
+            None => client
+                .execute(request)
+                .await
+                .map_err(|err| request_error(provider, started, err)),
+        };
+        let response = match result {
+            Ok(response) => response,
+            Err(err) => {
+                debug!(
+                    provider,
+                    %method,
+                    %url,
+                    latency_ms = started.elapsed().as_millis() as u64,
+                    error = %err,
+                    "Request failed"
+                );
+                return Err(err);
+            }
+        };
+        debug!(
+            provider,
+            %method,
+            %url,
+            status = response.status().as_u16(),
+            latency_ms = started.elapsed().as_millis() as u64,
+            "Received response"
+        );
+
+        if !response.status().is_success() {
+            return Err(error_from_response(provider, response).await);
+        }
+
+        Ok(response)
+    }
+
+    /// Send a request and deserialize its JSON body
+    pub(crate) async fn send_json<T: DeserializeOwned>(
+        &self,
+        provider: &str,
+        request: RequestBuilder,
+    ) -> Result<T> {
+        let started = Instant::now();
+        let response = self.send(provider, request).await?;
+        let body = response
+            .text()
+            .await
+            .map_err(|err| request_error(provider, started, err))?;
+        trace!(provider, body = %redact::body(&body), "Response body");
+
+        serde_json::from_str(&body).map_err(|err| {
+            debug!(
+                provider,
+                body = %redact::body(&body),
+                error = %err,
+                "Unparseable response body"
+            );
+            GatewayError::InvalidResponse(format!(
+                "{} returned an unparseable response: {}",
+                provider, err
+            ))
+        })
+    }
+}
+
+/// Map a transport error, reporting timeouts with the provider and elapsed time
+pub(crate) fn request_error(provider: &str, started: Instant, err: reqwest::Error) -> GatewayError {
+    if err.is_timeout() {
+        GatewayError::Timeout(format!(
+            "{} request timed out after {:.1}s",
+            provider,
+            started.elapsed().as_secs_f64()
+        ))
+    } else {
+        err.into()
+    }
+}
+
+/// Error codes that mean the prompt or completion was blocked by a content filter
+const CONTENT_FILTER_CODES: &[&str] = &[
+    "content_filter",
+    "content_policy_violation",
+    "ResponsibleAIPolicyViolation",
+    "DataInspectionFailed",
+    "data_inspection_failed",
+];
+
+/// Error codes that mean the prompt is too long for the model
+const CONTEXT_LENGTH_CODES: &[&str] = &["context_length_exceeded", "string_above_max_length"];
+
+/// Headers providers return the request ID in
+const REQUEST_ID_HEADERS: &[&str] = &[
+    "x-request-id",
+    "request-id",
+    "apim-request-id",
+    "x-dashscope-request-id",
+    "x-goog-request-id",
+];
+
+/// Convert a non-success HTTP response into a gateway error
+pub(crate) async fn error_from_response(provider: &str, response: Response) -> GatewayError {
+    let status = response.status();
+    let retry_after = parse_retry_after(response.headers());
+    let header_request_id = REQUEST_ID_HEADERS.iter().find_map(|name| {
+        response
+            .headers()
+            .get(*name)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string)
+    });
+    let body = response.text().await.unwrap_or_default();
+    debug!(provider, status = status.as_u16(), body = %redact::body(&body), "Error response");
+
+    let mut err = parse_error_body(provider, status, &body);
+    err.request_id = err.request_id.or(header_request_id);
+    err.retry_after = retry_after;
+    classify(err)
+}
+
+/// Parse an error body in any of the formats providers use
+///
+/// Handles OpenAI-style `{"error": {"message", "type", "code"}}` (also used by
+/// Azure, DeepSeek and compatible APIs), Anthropic's `{"type": "error", "error": {...}}`,
+/// Gemini's `{"error": {"code", "status", "message"}}`, DashScope's top-level
+/// `{"code", "message", "request_id"}` and Ollama's `{"error": "..."}`.
+fn parse_error_body(provider: &str, status: StatusCode, body: &str) -> ApiError {
+    let mut err = ApiError {
+        provider: provider.to_string(),
+        status,
+        code: None,
+        error_type: None,
+        message: body.trim().to_string(),
+        request_id: None,
+        retry_after: None,
+        details: None,
+    };
+
+    let Ok(value) = serde_json::from_str::<Value>(body) else {
+        return err;
+    };
+    let text = |value: Option<&Value>| match value {
+        Some(Value::String(s)) if !s.is_empty() => Some(s.clone()),
+        Some(Value::Number(n)) => Some(n.to_string()),
+        _ => None,
+    };
+
+    err.request_id = text(value.get("request_id"));
+
+    match value.get("error") {
+        Some(Value::String(message)) => err.message = message.clone(),
+        Some(error @ Value::Object(_)) => {
+            if let Some(message) = text(error.get("message")) {
+                err.message = message;
+            }
+            err.error_type = text(error.get("type")).or_else(|| text(error.get("status")));
+            // Gemini puts the HTTP status in `code` and the meaningful code in `status`
+            err.code = match error.get("code") {
+                Some(Value::String(code)) if !code.is_empty() => Some(code.clone()),
+                _ => err.error_type.clone(),
+            };
+
+            // Azure reports content filter results in `innererror`
+            if let Some(inner) = error.get("innererror") {
+                err.code = err.code.or_else(|| text(inner.get("code")));
+                err.details = inner.get("content_filter_result").cloned();
+            }
+            // Gemini gives the precise reason (e.g. `API_KEY_INVALID`) in `details`
+            if let Some(details) = error.get("details") {
+                if let Some(reason) = details
+                    .as_array()
+                    .and_then(|details| details.iter().find_map(|d| text(d.get("reason"))))
+                {
+                    err.code = Some(reason);
+                }
+                err.details = err.details.take().or_else(|| Some(details.clone()));
+            }
+        }
+        _ => {
+            err.code = text(value.get("code"));
+            if let Some(message) = text(value.get("message")) {
+                err.message = message;
+            }
+        }
+    }
+
+    err
+}
+
+/// Wrap an API error in the gateway error variant callers can branch on
+pub(crate) fn classify(err: ApiError) -> GatewayError {
+    let err = Box::new(err);
+    let code = err.code.as_deref().unwrap_or_default();
+    let error_type = err.error_type.as_deref().unwrap_or_default();
+    let message = err.message.to_lowercase();
+
+    if CONTENT_FILTER_CODES.contains(&code) {
+        GatewayError::ContentFiltered(err)
+    } else if CONTEXT_LENGTH_CODES.contains(&code)
+        || message.contains("maximum context length")
+        || message.contains("prompt is too long")
+        || message.contains("context window")
+    {
+        GatewayError::ContextLengthExceeded(err)
+    } else if err.status == StatusCode::UNAUTHORIZED
+        || err.status == StatusCode::FORBIDDEN
+        || matches!(
+            error_type,
+            "authentication_error" | "permission_error" | "invalid_api_key"
+        )
+        || matches!(code, "InvalidApiKey" | "API_KEY_INVALID")
+    {
+        GatewayError::Unauthorized(err)
+    } else if err.status == StatusCode::TOO_MANY_REQUESTS
+        || matches!(error_type, "rate_limit_error" | "RESOURCE_EXHAUSTED")
+        || code == "Throttling"
+    {
+        GatewayError::RateLimited(err)
+    } else if err.status == StatusCode::NOT_FOUND {
+        GatewayError::NotFound(err)
+    } else if err.status.is_client_error() && err.status != StatusCode::REQUEST_TIMEOUT {
+        GatewayError::BadRequest(err)
+    } else {
+        GatewayError::Api(err)
+    }
+}
+
+/// Read the server-requested retry delay from `retry-after-ms` or `Retry-After`
+///
+/// `Retry-After` may be a number of seconds or an HTTP date. Values that are
+/// not a valid duration, such as `inf`, are ignored.
+fn parse_retry_after(headers: &reqwest::header::HeaderMap) -> Option<Duration> {
+    let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+
+    if let Some(ms) = header("retry-after-ms").and_then(|v| v.trim().parse::<f64>().ok()) {
+        return Duration::try_from_secs_f64(ms.max(0.0) / 1000.0).ok();
+    }
+
+    let value = header(reqwest::header::RETRY_AFTER.as_str())?.trim();
+    match value.parse::<f64>() {
+        Ok(secs) => Duration::try_from_secs_f64(secs.max(0.0)).ok(),
+        Err(_) => httpdate::parse_http_date(value)
+            .ok()
+            .map(|date| date.duration_since(SystemTime::now()).unwrap_or_default()),
+    }
+}
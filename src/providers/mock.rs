@@ -0,0 +1,452 @@
+//! Scripted mock provider for tests
+//!
+//! The mock answers from a queue of [`MockStep`]s: canned replies, errors,
+//! delays and checks on the request received. It is selected like any other
+//! provider (`LLM_MODEL=mock/<model>`), so code built on the gateway can be
+//! tested without network access or API keys.
+//!
+//! In code, register a scripted instance with
+//! [`LLMGateway::with_provider`](crate::LLMGateway::with_provider) and keep a
+//! clone to inspect the requests it received:
+//!
+//! ```no_run
+//! # async fn example() -> fuckmit::Result<()> {
+//! use fuckmit::providers::mock::MockProvider;
+//! use fuckmit::{ChatMessage, GatewayConfig, LLMGateway, ProviderType};
+//!
+//! let mock = MockProvider::default().with_content("feat: add parser");
+//! let gateway = LLMGateway::new(GatewayConfig::new(ProviderType::Mock))?
+//!     .with_provider(mock.clone());
+//!
+//! let response = gateway
+//!     .chat_completion(vec![ChatMessage::user("hello")])
+//!     .await?;
+//! assert_eq!(response.content(), Some("feat: add parser"));
+//! assert_eq!(mock.requests().len(), 1);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! From configuration, the `response`, `script` and `delay_ms` extra settings
+//! (`MOCK_RESPONSE`, `MOCK_SCRIPT` and `MOCK_DELAY_MS`) set the reply, a JSON
+//! script file and a delay for every call.
+
+use crate::config::ProviderConfig;
+use crate::error::{ApiError, GatewayError, Result};
+use crate::providers::{http, Provider, ProviderType};
+use crate::stream::{single_chunk, ChatStream};
+use crate::tokenizer;
+use crate::types::{ChatChoice, ChatMessage, ChatRequest, ChatResponse, ModelInfo, Usage};
+use async_trait::async_trait;
+use reqwest::StatusCode;
+use serde::Deserialize;
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Reply used when configured without a response or script
+const DEFAULT_RESPONSE: &str = "Mock response";
+
+/// Check run on a received request; an `Err` fails the call with its message
+type Expectation = Box<dyn Fn(&ChatRequest) -> std::result::Result<(), String> + Send + Sync>;
+
+/// What a scripted step answers with
+enum Reply {
+    Content(String),
+    Response(Box<ChatResponse>),
+    Error(GatewayError),
+}
+
+/// One scripted call: a reply, an optional delay and an optional request check
+pub struct MockStep {
+    reply: Reply,
+    delay: Option<Duration>,
+    expectation: Option<Expectation>,
+}
+
+impl MockStep {
+    /// Reply with an assistant message
+    pub fn content(content: impl Into<String>) -> Self {
+        Self::new(Reply::Content(content.into()))
+    }
+
+    /// Reply with a complete response
+    pub fn response(response: ChatResponse) -> Self {
+        Self::new(Reply::Response(Box::new(response)))
+    }
+
+    /// Fail the call with an error
+    pub fn error(error: GatewayError) -> Self {
+        Self::new(Reply::Error(error))
+    }
+
+    fn new(reply: Reply) -> Self {
+        Self {
+            reply,
+            delay: None,
+            expectation: None,
+        }
+    }
+
+    /// Wait before replying
+    pub fn with_delay(mut self, delay: Duration) -> Self {
+        self.delay = Some(delay);
+        self
+    }
+
+    /// Check the request this step answers
+    ///
+    /// When the check returns an error the call fails with
+    /// [`GatewayError::InvalidRequest`] instead of replying.
+    pub fn with_expectation(
+        mut self,
+        expectation: impl Fn(&ChatRequest) -> std::result::Result<(), String> + Send + Sync + 'static,
+    ) -> Self {
+        self.expectation = Some(Box::new(expectation));
+        self
+    }
+}
+
+impl fmt::Debug for MockStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reply = match &self.reply {
+            Reply::Content(content) => format!("content({:?})", content),
+            Reply::Response(response) => format!("response({:?})", response.id),
+            Reply::Error(error) => format!("error({})", error),
+        };
+        f.debug_struct("MockStep")
+            .field("reply", &reply)
+            .field("delay", &self.delay)
+            .field("expectation", &self.expectation.is_some())
+            .finish()
+    }
+}
+
+#[derive(Default)]
+struct MockState {
+    steps: VecDeque<MockStep>,
+    requests: Vec<ChatRequest>,
+}
+
+/// Provider that replies from a script instead of calling an API
+///
+/// Clones share the script and the record of received requests.
+#[derive(Clone, Default)]
+pub struct MockProvider {
+    state: Arc<Mutex<MockState>>,
+    default_model: Option<String>,
+    default_response: Option<String>,
+    delay: Option<Duration>,
+}
+
+impl MockProvider {
+    /// Create a mock provider from its configuration
+    pub fn new(config: &ProviderConfig) -> Result<Self> {
+        let mut provider = Self {
+            default_model: config.default_model.clone(),
+            ..Self::default()
+        };
+
+        if let Some(delay) = config.extra.get("delay_ms") {
+            let millis = delay
+                .parse()
+                .map_err(|_| GatewayError::Config(format!("Invalid mock delay_ms: {}", delay)))?;
+            provider.delay = Some(Duration::from_millis(millis));
+        }
+
+        let script = config.extra.get("script");
+        if let Some(path) = script {
+            for step in load_script(Path::new(path))? {
+                provider = provider.with_step(step);
+            }
+        }
+
+        provider.default_response = match config.extra.get("response") {
+            Some(response) => Some(response.clone()),
+            None if script.is_none() => Some(DEFAULT_RESPONSE.to_string()),
+            None => None,
+        };
+
+        Ok(provider)
+    }
+
+    /// Add a step to the end of the script
+    pub fn with_step(self, step: MockStep) -> Self {
+        self.lock().steps.push_back(step);
+        self
+    }
+
+    /// Add a reply with an assistant message to the script
+    pub fn with_content(self, content: impl Into<String>) -> Self {
+        self.with_step(MockStep::content(content))
+    }
+
+    /// Add a complete response to the script
+    pub fn with_response(self, response: ChatResponse) -> Self {
+        self.with_step(MockStep::response(response))
+    }
+
+    /// Add a failing call to the script
+    pub fn with_error(self, error: GatewayError) -> Self {
+        self.with_step(MockStep::error(error))
+    }
+
+    /// Reply with `content` once the script is used up, instead of failing
+    pub fn with_default_response(mut self, content: impl Into<String>) -> Self {
+        self.default_response = Some(content.into());
+        self
+    }
+
+    /// Wait before every reply
+    pub fn with_delay(mut self, delay: Duration) -> Self {
+        self.delay = Some(delay);
+        self
+    }
+
+    /// Set the model reported by `default_model`
+    pub fn with_default_model(mut self, model: impl Into<String>) -> Self {
+        self.default_model = Some(model.into());
+        self
+    }
+
+    /// Requests received so far, in order
+    pub fn requests(&self) -> Vec<ChatRequest> {
+        self.lock().requests.clone()
+    }
+
+    /// Number of scripted steps not used yet
+    pub fn remaining(&self) -> usize {
+        self.lock().steps.len()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, MockState> {
+        // A panicking expectation must not break later assertions on the mock
+        self.state.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    /// Record a request and take the step that answers it
+    fn next_step(&self, request: &ChatRequest) -> Result<MockStep> {
+        let mut state = self.lock();
+        state.requests.push(request.clone());
+        match state.steps.pop_front() {
+            Some(step) => Ok(step),
+            None => match &self.default_response {
+                Some(content) => Ok(MockStep::content(content.clone())),
+                None => Err(GatewayError::InvalidRequest(format!(
+                    "Mock provider has no scripted reply for call {}",
+                    state.requests.len()
+                ))),
+            },
+        }
+    }
+}
+
+#[async_trait]
+impl Provider for MockProvider {
+    fn provider_type(&self) -> ProviderType {
+        ProviderType::Mock
+    }
+
+    async fn chat_completion(&self, request: ChatRequest) -> Result<ChatResponse> {
+        let step = self.next_step(&request)?;
+        if let Some(delay) = step.delay.or(self.delay) {
+            tokio::time::sleep(delay).await;
+        }
+
+        if let Some(expectation) = &step.expectation {
+            expectation(&request).map_err(|message| {
+                GatewayError::InvalidRequest(format!("Mock expectation failed: {}", message))
+            })?;
+        }
+
+        match step.reply {
+            Reply::Content(content) => Ok(response_for(&request, content)),
+            Reply::Response(response) => Ok(*response),
+            Reply::Error(error) => Err(error),
+        }
+    }
+
+    async fn chat_completion_stream(&self, request: ChatRequest) -> Result<ChatStream> {
+        Ok(single_chunk(self.chat_completion(request).await?))
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        Ok(self
+            .default_model()
+            .map(|model| vec![ModelInfo::new(model)])
+            .unwrap_or_default())
+    }
+
+    fn default_model(&self) -> Option<&str> {
+        self.default_model.as_deref().or(Some("mock"))
+    }
+
+    fn supports_streaming(&self) -> bool {
+        true
+    }
+
+    fn validate_config(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Build a response with `content` and estimated usage for `request`
+fn response_for(request: &ChatRequest, content: String) -> ChatResponse {
+    let created = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let prompt_tokens = request.estimated_prompt_tokens();
+    let completion_tokens = tokenizer::count_tokens(&request.model, &content);
+
+    ChatResponse {
+        id: format!("mock-{}", created),
+        object: "chat.completion".to_string(),
+        created,
+        model: request.model.clone(),
+        choices: vec![ChatChoice {
+            index: 0,
+            message: ChatMessage::assistant(content),
+            finish_reason: Some("stop".to_string()),
+        }],
+        usage: Usage {
+            prompt_tokens,
+            completion_tokens,
+            total_tokens: prompt_tokens + completion_tokens,
+        },
+        system_fingerprint: None,
+        provider: None,
+        cached: false,
+    }
+}
+
+/// A step in a JSON script file
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ScriptStep {
+    #[serde(default)]
+    content: Option<String>,
+    #[serde(default)]
+    error: Option<ScriptError>,
+    #[serde(default)]
+    delay_ms: Option<u64>,
+    #[serde(default)]
+    expect: Option<ScriptExpectation>,
+}
+
+/// An error response to fail a scripted call with
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ScriptError {
+    status: u16,
+    #[serde(default)]
+    message: Option<String>,
+    #[serde(default)]
+    code: Option<String>,
+    #[serde(default, rename = "type")]
+    error_type: Option<String>,
+}
+
+/// Checks on the request a scripted call receives
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ScriptExpectation {
+    #[serde(default)]
+    model: Option<String>,
+    #[serde(default)]
+    contains: Option<String>,
+}
+
+/// Load a script file: a JSON array of steps such as
+/// `{"content": "..."}`, `{"error": {"status": 429}}`, `{"delay_ms": 100}`
+/// and `{"expect": {"model": "...", "contains": "..."}}`
+fn load_script(path: &Path) -> Result<Vec<MockStep>> {
+    let text = fs::read_to_string(path).map_err(|err| {
+        GatewayError::Config(format!(
+            "Cannot read mock script {}: {}",
+            path.display(),
+            err
+        ))
+    })?;
+    let steps: Vec<ScriptStep> = serde_json::from_str(&text).map_err(|err| {
+        GatewayError::Config(format!("Invalid mock script {}: {}", path.display(), err))
+    })?;
+
+    steps
+        .into_iter()
+        .enumerate()
+        .map(|(index, step)| {
+            let mut mock_step = match (step.content, step.error) {
+                (Some(_), Some(_)) => {
+                    return Err(GatewayError::Config(format!(
+                        "Mock script step {} has both content and error",
+                        index + 1
+                    )))
+                }
+                (_, Some(error)) => MockStep::error(script_error(error)?),
+                (content, None) => {
+                    MockStep::content(content.unwrap_or_else(|| DEFAULT_RESPONSE.to_string()))
+                }
+            };
+
+            if let Some(delay_ms) = step.delay_ms {
+                mock_step = mock_step.with_delay(Duration::from_millis(delay_ms));
+            }
+
+            if let Some(expect) = step.expect {
+                mock_step = mock_step.with_expectation(move |request| expect.check(request));
+            }
+
+            Ok(mock_step)
+        })
+        .collect()
+}
+
+/// Turn a scripted error into the gateway error a provider would return
+fn script_error(error: ScriptError) -> Result<GatewayError> {
+    let status = StatusCode::from_u16(error.status).map_err(|_| {
+        GatewayError::Config(format!("Invalid status in mock script: {}", error.status))
+    })?;
+    let message = error.message.unwrap_or_else(|| {
+        status
+            .canonical_reason()
+            .unwrap_or("Mock error")
+            .to_string()
+    });
+
+    Ok(http::classify(ApiError {
+        provider: "mock".to_string(),
+        status,
+        code: error.code,
+        error_type: error.error_type,
+        message,
+        request_id: None,
+        retry_after: None,
+        details: None,
+    }))
+}
+
+impl ScriptExpectation {
+    fn check(&self, request: &ChatRequest) -> std::result::Result<(), String> {
+        if let Some(model) = &self.model {
+            if &request.model != model {
+                return Err(format!("expected model {}, got {}", model, request.model));
+            }
+        }
+
+        if let Some(needle) = &self.contains {
+            if !request
+                .messages
+                .iter()
+                .any(|message| message.content.contains(needle.as_str()))
+            {
+                return Err(format!("no message contains {:?}", needle));
+            }
+        }
+
+        Ok(())
+    }
+}
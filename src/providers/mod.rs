@@ -1,19 +1,26 @@
 //! AI provider implementations
 
-use crate::error::Result;
-use crate::types::{ChatRequest, ChatResponse};
+use crate::error::{GatewayError, Result};
+use crate::stream::ChatStream;
+use crate::types::{ChatRequest, ChatResponse, ModelInfo, ResponseFormat};
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use std::fmt;
 use std::str::FromStr;
 
+pub mod anthropic;
 pub mod azure;
-pub mod deepseek;
-pub mod openai;
-pub mod qwen;
+pub mod compatible;
+pub mod gemini;
+pub(crate) mod http;
+pub mod mock;
+pub mod ollama;
 
 /// Supported AI provider types
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+///
+/// Serialized as the lowercase provider name, e.g. `"openai"`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
 pub enum ProviderType {
     /// OpenAI GPT models
     OpenAI,
@@ -23,6 +30,16 @@ pub enum ProviderType {
     DeepSeek,
     /// Alibaba Qwen models
     Qwen,
+    /// Anthropic Claude models
+    Anthropic,
+    /// Google Gemini models
+    Gemini,
+    /// Local models served by Ollama
+    Ollama,
+    /// Scripted replies for tests, no API calls
+    Mock,
+    /// User-defined OpenAI-compatible provider, identified by name
+    Custom(String),
 }
 
 impl fmt::Display for ProviderType {
@@ -32,6 +49,11 @@ impl fmt::Display for ProviderType {
             ProviderType::Azure => write!(f, "azure"),
             ProviderType::DeepSeek => write!(f, "deepseek"),
             ProviderType::Qwen => write!(f, "qwen"),
+            ProviderType::Anthropic => write!(f, "anthropic"),
+            ProviderType::Gemini => write!(f, "gemini"),
+            ProviderType::Ollama => write!(f, "ollama"),
+            ProviderType::Mock => write!(f, "mock"),
+            ProviderType::Custom(name) => write!(f, "{}", name),
         }
     }
 }
@@ -45,11 +67,38 @@ impl FromStr for ProviderType {
             "azure" => Ok(ProviderType::Azure),
             "deepseek" => Ok(ProviderType::DeepSeek),
             "qwen" => Ok(ProviderType::Qwen),
+            "anthropic" => Ok(ProviderType::Anthropic),
+            "gemini" => Ok(ProviderType::Gemini),
+            "ollama" => Ok(ProviderType::Ollama),
+            "mock" => Ok(ProviderType::Mock),
+            name if is_valid_custom_name(name) => Ok(ProviderType::Custom(name.to_string())),
             _ => Err(format!("Unknown provider type: {}", s)),
         }
     }
 }
 
+impl TryFrom<String> for ProviderType {
+    type Error = String;
+
+    fn try_from(value: String) -> std::result::Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<ProviderType> for String {
+    fn from(provider_type: ProviderType) -> Self {
+        provider_type.to_string()
+    }
+}
+
+/// Custom provider names are lowercase identifiers such as `moonshot` or `my-gateway`
+fn is_valid_custom_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
 /// Trait that all AI providers must implement
 #[async_trait]
 pub trait Provider: Send + Sync {
@@ -62,12 +111,38 @@ pub trait Provider: Send + Sync {
     /// Get the default model for this provider
     fn default_model(&self) -> Option<&str>;
 
+    /// Send a streaming chat completion request
+    async fn chat_completion_stream(&self, _request: ChatRequest) -> Result<ChatStream> {
+        Err(GatewayError::InvalidRequest(format!(
+            "Provider {} does not support streaming",
+            self.provider_type()
+        )))
+    }
+
+    /// List the models the provider offers
+    async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        Err(GatewayError::InvalidRequest(format!(
+            "Provider {} does not support listing models",
+            self.provider_type()
+        )))
+    }
+
     /// Check if the provider supports streaming
     fn supports_streaming(&self) -> bool {
         false
     }
 
+    /// Check if the provider can generate several choices (`n`) in one call
+    ///
+    /// For other providers the gateway makes one call per choice.
+    fn supports_n(&self) -> bool {
+        false
+    }
+
     /// Validate the configuration for this provider
+    ///
+    /// Hosted providers require a non-empty API key; local ones such as
+    /// Ollama do not.
     fn validate_config(&self) -> Result<()>;
 }
 
@@ -77,9 +152,41 @@ pub fn create_provider(
     config: &crate::config::ProviderConfig,
 ) -> Result<Box<dyn Provider>> {
     match provider_type {
-        ProviderType::OpenAI => Ok(Box::new(openai::OpenAIProvider::new(config)?)),
+        ProviderType::OpenAI => Ok(Box::new(compatible::CompatibleProvider::with_preset(
+            &compatible::OPENAI,
+            config,
+        )?)),
         ProviderType::Azure => Ok(Box::new(azure::AzureProvider::new(config)?)),
-        ProviderType::DeepSeek => Ok(Box::new(deepseek::DeepSeekProvider::new(config)?)),
-        ProviderType::Qwen => Ok(Box::new(qwen::QwenProvider::new(config)?)),
+        ProviderType::DeepSeek => Ok(Box::new(compatible::CompatibleProvider::with_preset(
+            &compatible::DEEPSEEK,
+            config,
+        )?)),
+        ProviderType::Qwen => Ok(Box::new(compatible::CompatibleProvider::with_preset(
+            &compatible::QWEN,
+            config,
+        )?)),
+        ProviderType::Anthropic => Ok(Box::new(anthropic::AnthropicProvider::new(config)?)),
+        ProviderType::Gemini => Ok(Box::new(gemini::GeminiProvider::new(config)?)),
+        ProviderType::Ollama => Ok(Box::new(ollama::OllamaProvider::new(config)?)),
+        ProviderType::Mock => Ok(Box::new(mock::MockProvider::new(config)?)),
+        ProviderType::Custom(name) => Ok(Box::new(compatible::CompatibleProvider::new(
+            &name, config,
+        )?)),
+    }
+}
+
+/// Response body of an OpenAI-compatible `/models` endpoint
+#[derive(Debug, Deserialize)]
+pub(crate) struct ModelList {
+    pub data: Vec<ModelInfo>,
+}
+
+/// Replace a `json_schema` response format with plain `json_object` for
+/// providers that only support the latter
+///
+/// The schema itself is still enforced by [`crate::LLMGateway::chat_structured`].
+pub(crate) fn json_object_only(request: &mut ChatRequest) {
+    if let Some(ResponseFormat::JsonSchema { .. }) = request.response_format {
+        request.response_format = Some(ResponseFormat::JsonObject);
     }
 }
@@ -0,0 +1,284 @@
+//! Ollama provider implementation
+
+use crate::config::ProviderConfig;
+use crate::credentials::ApiKey;
+use crate::error::{GatewayError, Result};
+use crate::providers::{http, Provider, ProviderType};
+use crate::types::{
+    ChatChoice, ChatMessage, ChatRequest, ChatResponse, MessageRole, ResponseFormat, Tool,
+    ToolCall, Usage,
+};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Ollama provider
+pub struct OllamaProvider {
+    client: http::HttpClient,
+    api_key: ApiKey,
+    base_url: String,
+    default_model: Option<String>,
+    num_ctx: Option<u32>,
+    keep_alive: Option<String>,
+}
+
+impl OllamaProvider {
+    /// Create a new Ollama provider
+    pub fn new(config: &ProviderConfig) -> Result<Self> {
+        let base_url = config
+            .base_url
+            .clone()
+            .unwrap_or_else(|| "http://localhost:11434".to_string());
+
+        let num_ctx = config
+            .extra
+            .get("num_ctx")
+            .map(|value| {
+                value
+                    .parse()
+                    .map_err(|_| GatewayError::Config(format!("Invalid Ollama num_ctx: {}", value)))
+            })
+            .transpose()?;
+
+        Ok(Self {
+            client: http::build_client(config)?,
+            api_key: config.api_key.clone(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            default_model: config.default_model.clone(),
+            num_ctx,
+            keep_alive: config.extra.get("keep_alive").cloned(),
+        })
+    }
+}
+
+/// Request body for `/api/chat`
+#[derive(Debug, Serialize)]
+struct OllamaChatRequest {
+    model: String,
+    messages: Vec<OllamaMessage>,
+    stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    options: Option<OllamaOptions>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    keep_alive: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tools: Option<Vec<Tool>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    format: Option<Value>,
+    #[serde(flatten)]
+    extra: HashMap<String, Value>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct OllamaMessage {
+    role: String,
+    #[serde(default)]
+    content: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tool_calls: Option<Vec<OllamaToolCall>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tool_name: Option<String>,
+}
+
+/// Ollama tool calls carry arguments as a JSON object and have no ID
+#[derive(Debug, Serialize, Deserialize)]
+struct OllamaToolCall {
+    function: OllamaFunctionCall,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct OllamaFunctionCall {
+    name: String,
+    #[serde(default)]
+    arguments: Value,
+}
+
+#[derive(Debug, Default, Serialize)]
+struct OllamaOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    num_ctx: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    num_predict: Option<u32>,
+}
+
+/// Response body from `/api/chat`
+#[derive(Debug, Deserialize)]
+struct OllamaChatResponse {
+    model: String,
+    message: OllamaMessage,
+    #[serde(default)]
+    done_reason: Option<String>,
+    #[serde(default)]
+    prompt_eval_count: u32,
+    #[serde(default)]
+    eval_count: u32,
+}
+
+impl OllamaProvider {
+    fn build_request(&self, request: ChatRequest) -> OllamaChatRequest {
+        // Tool results must name the function; remember it by call ID
+        let mut call_names = HashMap::new();
+        let messages = request
+            .messages
+            .into_iter()
+            .map(|message| {
+                let tool_name = match message.role {
+                    MessageRole::Function | MessageRole::Tool => message.name.or_else(|| {
+                        message
+                            .tool_call_id
+                            .as_ref()
+                            .and_then(|id| call_names.get(id).cloned())
+                    }),
+                    _ => None,
+                };
+                let tool_calls = message.tool_calls.map(|calls| {
+                    calls
+                        .into_iter()
+                        .map(|call| {
+                            call_names.insert(call.id, call.function.name.clone());
+                            OllamaToolCall {
+                                function: OllamaFunctionCall {
+                                    name: call.function.name,
+                                    arguments: serde_json::from_str(&call.function.arguments)
+                                        .unwrap_or_else(|_| json!({})),
+                                },
+                            }
+                        })
+                        .collect()
+                });
+
+                OllamaMessage {
+                    role: match message.role {
+                        MessageRole::System => "system",
+                        MessageRole::User => "user",
+                        MessageRole::Assistant => "assistant",
+                        MessageRole::Function | MessageRole::Tool => "tool",
+                    }
+                    .to_string(),
+                    content: message.content,
+                    tool_calls,
+                    tool_name,
+                }
+            })
+            .collect();
+
+        let options = OllamaOptions {
+            num_ctx: self.num_ctx,
+            temperature: request.temperature,
+            top_p: request.top_p,
+            num_predict: request.max_tokens,
+        };
+        let has_options = options.num_ctx.is_some()
+            || options.temperature.is_some()
+            || options.top_p.is_some()
+            || options.num_predict.is_some();
+
+        // Ollama takes either "json" or the schema itself
+        let format = match request.response_format {
+            Some(ResponseFormat::JsonObject) => Some(json!("json")),
+            Some(ResponseFormat::JsonSchema { json_schema }) => Some(json_schema.schema),
+            _ => None,
+        };
+
+        OllamaChatRequest {
+            model: request.model,
+            messages,
+            stream: false,
+            options: has_options.then_some(options),
+            keep_alive: self.keep_alive.clone(),
+            tools: request.tools,
+            format,
+            extra: request.extra,
+        }
+    }
+}
+
+impl From<OllamaChatResponse> for ChatResponse {
+    fn from(response: OllamaChatResponse) -> Self {
+        let created = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+
+        let mut message = ChatMessage::assistant(response.message.content);
+        message.tool_calls = response.message.tool_calls.map(|calls| {
+            calls
+                .into_iter()
+                .enumerate()
+                .map(|(i, call)| {
+                    ToolCall::function(
+                        format!("call_{}", i),
+                        call.function.name,
+                        call.function.arguments.to_string(),
+                    )
+                })
+                .collect()
+        });
+
+        ChatResponse {
+            id: String::new(),
+            object: "chat.completion".to_string(),
+            created,
+            model: response.model,
+            choices: vec![ChatChoice {
+                index: 0,
+                message,
+                finish_reason: response.done_reason,
+            }],
+            usage: Usage {
+                prompt_tokens: response.prompt_eval_count,
+                completion_tokens: response.eval_count,
+                total_tokens: response.prompt_eval_count + response.eval_count,
+            },
+            system_fingerprint: None,
+            provider: None,
+            cached: false,
+        }
+    }
+}
+
+#[async_trait]
+impl Provider for OllamaProvider {
+    fn provider_type(&self) -> ProviderType {
+        ProviderType::Ollama
+    }
+
+    async fn chat_completion(&self, request: ChatRequest) -> Result<ChatResponse> {
+        let url = format!("{}/api/chat", self.base_url);
+        let body = self.build_request(request);
+
+        let mut request = self
+            .client
+            .post(&url)
+            .header("Content-Type", "application/json")
+            .json(&body);
+
+        // Ollama itself is unauthenticated, but it is often put behind a proxy that is not
+        if !self.api_key.is_empty() {
+            let api_key = self.api_key.resolve().await?;
+            request = request.header("Authorization", format!("Bearer {}", api_key));
+        }
+
+        let response: OllamaChatResponse = self.client.send_json("Ollama", request).await?;
+        Ok(response.into())
+    }
+
+    fn default_model(&self) -> Option<&str> {
+        self.default_model.as_deref().or(Some("llama3.2"))
+    }
+
+    fn validate_config(&self) -> Result<()> {
+        if self.base_url.is_empty() {
+            return Err(GatewayError::Config(
+                "Ollama base URL is required".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
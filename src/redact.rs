@@ -0,0 +1,97 @@
+//! Removal of credentials from logged requests and responses
+
+use reqwest::header::HeaderMap;
+use reqwest::Url;
+use serde_json::Value;
+
+/// Replacement for redacted values
+const REDACTED: &str = "<redacted>";
+
+/// Name segments that mark a header, query parameter or JSON field as a credential
+const SENSITIVE_SEGMENTS: &[&str] = &[
+    "apikey",
+    "auth",
+    "authorization",
+    "cookie",
+    "credential",
+    "key",
+    "password",
+    "secret",
+    "signature",
+    "token",
+];
+
+/// Whether a header, query parameter or field name looks like it carries a credential
+pub(crate) fn is_sensitive(name: &str) -> bool {
+    name.to_ascii_lowercase()
+        .split(['-', '_', '.'])
+        .any(|segment| SENSITIVE_SEGMENTS.contains(&segment))
+}
+
+/// URL with its password and credential query parameters removed
+pub(crate) fn url(url: &Url) -> String {
+    let mut url = url.clone();
+    if url.password().is_some() {
+        let _ = url.set_password(Some(REDACTED));
+    }
+
+    if url.query_pairs().any(|(name, _)| is_sensitive(&name)) {
+        let pairs: Vec<(String, String)> = url
+            .query_pairs()
+            .map(|(name, value)| {
+                let value = if is_sensitive(&name) {
+                    REDACTED.to_string()
+                } else {
+                    value.into_owned()
+                };
+                (name.into_owned(), value)
+            })
+            .collect();
+        url.query_pairs_mut().clear().extend_pairs(pairs);
+    }
+
+    url.to_string()
+}
+
+/// Headers as `name: value` pairs with credential values replaced
+pub(crate) fn headers(headers: &HeaderMap) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            let value = if is_sensitive(name.as_str()) {
+                REDACTED.to_string()
+            } else {
+                String::from_utf8_lossy(value.as_bytes()).into_owned()
+            };
+            (name.to_string(), value)
+        })
+        .collect()
+}
+
+/// Body with credential fields replaced, if it is JSON
+pub(crate) fn body(body: &str) -> String {
+    match serde_json::from_str::<Value>(body) {
+        Ok(mut value) => {
+            self::value(&mut value);
+            value.to_string()
+        }
+        Err(_) => body.to_string(),
+    }
+}
+
+/// Replace credential fields anywhere in a JSON value
+pub(crate) fn value(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for (name, value) in map.iter_mut() {
+                if is_sensitive(name) && (value.is_string() || value.is_number()) {
+                    *value = Value::String(REDACTED.to_string());
+                } else {
+                    self::value(value);
+                }
+            }
+        }
+        Value::Array(values) => values.iter_mut().for_each(self::value),
+        _ => {}
+    }
+}
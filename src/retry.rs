@@ -0,0 +1,92 @@
+//! Retry policy for transient provider failures
+
+use crate::error::{GatewayError, Result};
+use std::future::Future;
+use std::time::Duration;
+use tracing::{warn, Instrument};
+
+/// Number of retries used when `max_retries` is not configured
+pub const DEFAULT_MAX_RETRIES: u32 = 2;
+
+/// Upper bound for a server-requested `Retry-After` delay
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(60);
+
+/// Exponential backoff with jitter
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Maximum number of retries after the first attempt
+    pub max_retries: u32,
+    /// Delay before the first retry
+    pub base_delay: Duration,
+    /// Upper bound for the computed backoff delay
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_RETRIES)
+    }
+}
+
+impl RetryPolicy {
+    /// Create a retry policy with default delays
+    pub fn new(max_retries: u32) -> Self {
+        Self {
+            max_retries,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(20),
+        }
+    }
+
+    /// Disable retries
+    pub fn none() -> Self {
+        Self::new(0)
+    }
+
+    /// Delay before retry number `attempt` (starting at 0) after `error`
+    ///
+    /// A `Retry-After` hint from the server takes precedence over the
+    /// computed backoff.
+    pub fn delay(&self, attempt: u32, error: &GatewayError) -> Duration {
+        if let Some(retry_after) = error.retry_after() {
+            return retry_after.min(MAX_RETRY_AFTER);
+        }
+
+        let exp = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_delay);
+
+        // Equal jitter: keep half of the delay and randomize the rest
+        let half = exp / 2;
+        half + half.mul_f64(fastrand::f64())
+    }
+
+    /// Run `op` until it succeeds, fails with a non-retryable error or
+    /// runs out of retries
+    pub async fn run<T, F, Fut>(&self, mut op: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let mut attempt = 0;
+        loop {
+            let span = tracing::info_span!("attempt", attempt = attempt + 1);
+            match op().instrument(span).await {
+                Ok(value) => return Ok(value),
+                Err(err) if attempt < self.max_retries && err.is_retryable() => {
+                    let delay = self.delay(attempt, &err);
+                    warn!(
+                        attempt = attempt + 1,
+                        delay_ms = delay.as_millis() as u64,
+                        error = %err,
+                        "Retrying after a retryable error"
+                    );
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+}
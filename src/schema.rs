@@ -0,0 +1,213 @@
+//! Lightweight JSON Schema validation for structured replies
+//!
+//! Supports the subset of JSON Schema that providers accept for structured
+//! output: `type`, `enum`, `const`, `properties`, `required`,
+//! `additionalProperties`, `items`, `anyOf`/`oneOf`/`allOf`, string length,
+//! numeric bounds and array length. Unknown keywords are ignored.
+
+use serde_json::{Map, Value};
+
+/// Validate `value` against `schema`, describing the first violation found
+pub fn validate(schema: &Value, value: &Value) -> std::result::Result<(), String> {
+    validate_at(schema, value, "$")
+}
+
+fn validate_at(schema: &Value, value: &Value, path: &str) -> std::result::Result<(), String> {
+    let schema = match schema {
+        Value::Bool(true) => return Ok(()),
+        Value::Bool(false) => return Err(format!("{}: no value is allowed here", path)),
+        Value::Object(schema) => schema,
+        _ => return Ok(()),
+    };
+
+    if let Some(expected) = schema.get("type") {
+        let matches = match expected {
+            Value::String(name) => has_type(value, name),
+            Value::Array(names) => names
+                .iter()
+                .filter_map(Value::as_str)
+                .any(|name| has_type(value, name)),
+            _ => true,
+        };
+        if !matches {
+            return Err(format!(
+                "{}: expected type {}, got {}",
+                path,
+                expected,
+                type_name(value)
+            ));
+        }
+    }
+
+    if let Some(Value::Array(allowed)) = schema.get("enum") {
+        if !allowed.contains(value) {
+            return Err(format!(
+                "{}: {} is not one of {}",
+                path,
+                value,
+                Value::Array(allowed.clone())
+            ));
+        }
+    }
+
+    if let Some(expected) = schema.get("const") {
+        if expected != value {
+            return Err(format!("{}: expected {}, got {}", path, expected, value));
+        }
+    }
+
+    if let Some(Value::Array(all)) = schema.get("allOf") {
+        for sub in all {
+            validate_at(sub, value, path)?;
+        }
+    }
+
+    for keyword in ["anyOf", "oneOf"] {
+        if let Some(Value::Array(options)) = schema.get(keyword) {
+            let errors: Vec<String> = options
+                .iter()
+                .filter_map(|sub| validate_at(sub, value, path).err())
+                .collect();
+            if errors.len() == options.len() {
+                return Err(format!(
+                    "{}: does not match any allowed schema ({})",
+                    path,
+                    errors.join("; ")
+                ));
+            }
+        }
+    }
+
+    match value {
+        Value::Object(object) => validate_object(schema, object, path),
+        Value::Array(items) => validate_array(schema, items, path),
+        Value::String(text) => validate_string(schema, text, path),
+        Value::Number(number) => validate_number(schema, number.as_f64().unwrap_or_default(), path),
+        _ => Ok(()),
+    }
+}
+
+fn validate_object(
+    schema: &Map<String, Value>,
+    object: &Map<String, Value>,
+    path: &str,
+) -> std::result::Result<(), String> {
+    if let Some(Value::Array(required)) = schema.get("required") {
+        for key in required.iter().filter_map(Value::as_str) {
+            if !object.contains_key(key) {
+                return Err(format!("{}: missing required property \"{}\"", path, key));
+            }
+        }
+    }
+
+    let properties = schema.get("properties").and_then(Value::as_object);
+    let additional = schema.get("additionalProperties");
+
+    for (key, item) in object {
+        let item_path = format!("{}.{}", path, key);
+        match properties.and_then(|properties| properties.get(key)) {
+            Some(sub) => validate_at(sub, item, &item_path)?,
+            None => match additional {
+                Some(Value::Bool(false)) => {
+                    return Err(format!("{}: unexpected property \"{}\"", path, key))
+                }
+                Some(sub @ Value::Object(_)) => validate_at(sub, item, &item_path)?,
+                _ => {}
+            },
+        }
+    }
+
+    Ok(())
+}
+
+fn validate_array(
+    schema: &Map<String, Value>,
+    items: &[Value],
+    path: &str,
+) -> std::result::Result<(), String> {
+    if let Some(min) = schema.get("minItems").and_then(Value::as_u64) {
+        if (items.len() as u64) < min {
+            return Err(format!("{}: expected at least {} items", path, min));
+        }
+    }
+
+    if let Some(max) = schema.get("maxItems").and_then(Value::as_u64) {
+        if items.len() as u64 > max {
+            return Err(format!("{}: expected at most {} items", path, max));
+        }
+    }
+
+    if let Some(item_schema) = schema.get("items") {
+        for (i, item) in items.iter().enumerate() {
+            validate_at(item_schema, item, &format!("{}[{}]", path, i))?;
+        }
+    }
+
+    Ok(())
+}
+
+fn validate_string(
+    schema: &Map<String, Value>,
+    text: &str,
+    path: &str,
+) -> std::result::Result<(), String> {
+    let len = text.chars().count() as u64;
+
+    if let Some(min) = schema.get("minLength").and_then(Value::as_u64) {
+        if len < min {
+            return Err(format!("{}: expected at least {} characters", path, min));
+        }
+    }
+
+    if let Some(max) = schema.get("maxLength").and_then(Value::as_u64) {
+        if len > max {
+            return Err(format!("{}: expected at most {} characters", path, max));
+        }
+    }
+
+    Ok(())
+}
+
+fn validate_number(
+    schema: &Map<String, Value>,
+    number: f64,
+    path: &str,
+) -> std::result::Result<(), String> {
+    if let Some(min) = schema.get("minimum").and_then(Value::as_f64) {
+        if number < min {
+            return Err(format!("{}: {} is less than {}", path, number, min));
+        }
+    }
+
+    if let Some(max) = schema.get("maximum").and_then(Value::as_f64) {
+        if number > max {
+            return Err(format!("{}: {} is greater than {}", path, number, max));
+        }
+    }
+
+    Ok(())
+}
+
+fn has_type(value: &Value, name: &str) -> bool {
+    match name {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "boolean" => value.is_boolean(),
+        "null" => value.is_null(),
+        "number" => value.is_number(),
+        "integer" => value.is_i64() || value.is_u64(),
+        _ => true,
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
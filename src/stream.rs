@@ -0,0 +1,129 @@
+//! Streaming support for chat completions
+
+use crate::error::{GatewayError, Result};
+use crate::providers::http::request_error;
+use crate::redact;
+use crate::types::{ChatCompletionChunk, ChatResponse};
+use futures::stream::{self, BoxStream, StreamExt};
+use std::collections::VecDeque;
+use std::time::Instant;
+use tracing::trace;
+
+/// Async stream of chat completion chunks
+pub type ChatStream = BoxStream<'static, Result<ChatCompletionChunk>>;
+
+/// Wrap a complete response into a stream that yields a single chunk
+pub fn single_chunk(response: ChatResponse) -> ChatStream {
+    stream::once(async move { Ok(ChatCompletionChunk::from(response)) }).boxed()
+}
+
+/// Incremental decoder for `text/event-stream` bodies
+#[derive(Debug, Default)]
+struct SseDecoder {
+    buffer: Vec<u8>,
+    data: Vec<String>,
+}
+
+impl SseDecoder {
+    /// Feed raw bytes and collect the data payloads of every completed event
+    fn push(&mut self, bytes: &[u8], events: &mut VecDeque<String>) {
+        self.buffer.extend_from_slice(bytes);
+
+        while let Some(pos) = self.buffer.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=pos).collect();
+            let line = String::from_utf8_lossy(&line);
+            self.line(line.trim_end_matches(['\r', '\n']), events);
+        }
+    }
+
+    /// Flush whatever is left once the body has ended
+    fn finish(&mut self, events: &mut VecDeque<String>) {
+        if !self.buffer.is_empty() {
+            let line = String::from_utf8_lossy(&std::mem::take(&mut self.buffer)).into_owned();
+            self.line(line.trim_end_matches('\r'), events);
+        }
+        self.dispatch(events);
+    }
+
+    fn line(&mut self, line: &str, events: &mut VecDeque<String>) {
+        if line.is_empty() {
+            self.dispatch(events);
+        } else if let Some(data) = line.strip_prefix("data:") {
+            self.data
+                .push(data.strip_prefix(' ').unwrap_or(data).to_string());
+        }
+        // Comments (`:`) and other fields (`event`, `id`, `retry`) are ignored
+    }
+
+    fn dispatch(&mut self, events: &mut VecDeque<String>) {
+        if !self.data.is_empty() {
+            events.push_back(self.data.join("\n"));
+            self.data.clear();
+        }
+    }
+}
+
+/// Parse a single event payload into a chunk
+fn parse_event(provider: &str, data: &str) -> Result<ChatCompletionChunk> {
+    trace!(provider, data = %redact::body(data), "Stream event");
+    let value: serde_json::Value = serde_json::from_str(data)?;
+    if let Some(error) = value.get("error") {
+        return Err(GatewayError::Provider(format!(
+            "{} stream error: {}",
+            provider, error
+        )));
+    }
+    Ok(serde_json::from_value(value)?)
+}
+
+/// Turn an OpenAI-compatible server-sent events response into a chunk stream
+pub(crate) fn sse_stream(provider: &str, response: reqwest::Response) -> ChatStream {
+    struct State {
+        provider: String,
+        body: BoxStream<'static, reqwest::Result<bytes::Bytes>>,
+        decoder: SseDecoder,
+        events: VecDeque<String>,
+        done: bool,
+        started: Instant,
+    }
+
+    let state = State {
+        provider: provider.to_string(),
+        body: response.bytes_stream().boxed(),
+        decoder: SseDecoder::default(),
+        events: VecDeque::new(),
+        done: false,
+        started: Instant::now(),
+    };
+
+    stream::unfold(state, |mut state| async move {
+        loop {
+            if let Some(data) = state.events.pop_front() {
+                if data.trim() == "[DONE]" {
+                    state.events.clear();
+                    state.done = true;
+                    continue;
+                }
+                return Some((parse_event(&state.provider, &data), state));
+            }
+
+            if state.done {
+                return None;
+            }
+
+            match state.body.next().await {
+                Some(Ok(bytes)) => state.decoder.push(&bytes, &mut state.events),
+                Some(Err(err)) => {
+                    state.done = true;
+                    let err = request_error(&state.provider, state.started, err);
+                    return Some((Err(err), state));
+                }
+                None => {
+                    state.decoder.finish(&mut state.events);
+                    state.done = true;
+                }
+            }
+        }
+    })
+    .boxed()
+}
@@ -0,0 +1,201 @@
+//! Local token counting and context window lookup
+//!
+//! Counts are exact for OpenAI models (cl100k/o200k BPE) and estimated for
+//! everyone else by scaling the cl100k count, which is close enough to keep
+//! requests inside the context window.
+
+use crate::types::{ChatMessage, ChatRequest};
+use tiktoken_rs::{cl100k_base_singleton, o200k_base_singleton};
+
+/// Tokens added for every message by the chat format
+const TOKENS_PER_MESSAGE: u32 = 3;
+
+/// Tokens added when a message carries a name
+const TOKENS_PER_NAME: u32 = 1;
+
+/// Tokens that prime the assistant's reply
+const REPLY_PRIMING_TOKENS: u32 = 3;
+
+/// Safety factor applied to cl100k counts for models with other tokenizers
+const APPROXIMATION_FACTOR: f64 = 1.1;
+
+/// Tokenizer used to count tokens for a model
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    /// `cl100k_base` (GPT-4, GPT-3.5)
+    Cl100k,
+    /// `o200k_base` (GPT-4o, GPT-4.1, o-series, GPT-5)
+    O200k,
+    /// Scaled `cl100k_base` estimate (DeepSeek, Qwen and other vendors)
+    Approximate,
+}
+
+impl Encoding {
+    /// Pick the encoding for a model name
+    pub fn for_model(model: &str) -> Self {
+        let model = model_name(model);
+        if [
+            "gpt-4o",
+            "gpt-4.1",
+            "gpt-4.5",
+            "gpt-5",
+            "o1",
+            "o3",
+            "o4",
+            "chatgpt-4o",
+        ]
+        .iter()
+        .any(|prefix| model.starts_with(prefix))
+        {
+            Encoding::O200k
+        } else if ["gpt-4", "gpt-3.5", "gpt-35", "text-embedding"]
+            .iter()
+            .any(|prefix| model.starts_with(prefix))
+        {
+            Encoding::Cl100k
+        } else {
+            Encoding::Approximate
+        }
+    }
+
+    /// Count the tokens in `text`
+    pub fn count(&self, text: &str) -> u32 {
+        match self {
+            Encoding::Cl100k => cl100k_base_singleton().encode_ordinary(text).len() as u32,
+            Encoding::O200k => o200k_base_singleton().encode_ordinary(text).len() as u32,
+            Encoding::Approximate => {
+                let count = cl100k_base_singleton().encode_ordinary(text).len() as f64;
+                (count * APPROXIMATION_FACTOR).ceil() as u32
+            }
+        }
+    }
+}
+
+/// Count the tokens in `text` for `model`
+pub fn count_tokens(model: &str, text: &str) -> u32 {
+    Encoding::for_model(model).count(text)
+}
+
+/// Estimate the prompt tokens of a list of messages for `model`
+pub fn count_message_tokens(model: &str, messages: &[ChatMessage]) -> u32 {
+    let encoding = Encoding::for_model(model);
+
+    let message_tokens: u32 = messages
+        .iter()
+        .map(|message| {
+            let mut tokens = TOKENS_PER_MESSAGE + encoding.count(&message.content);
+            if let Some(name) = &message.name {
+                tokens += TOKENS_PER_NAME + encoding.count(name);
+            }
+            for call in message.tool_calls() {
+                tokens += encoding.count(&call.function.name);
+                tokens += encoding.count(&call.function.arguments);
+            }
+            tokens
+        })
+        .sum();
+
+    message_tokens + REPLY_PRIMING_TOKENS
+}
+
+/// Context window of a known model, in tokens
+///
+/// Unknown models have no known limit, rather than the window of a family
+/// member with a similar name.
+pub fn context_window(model: &str) -> Option<u32> {
+    let model = model_name(model);
+
+    // A trailing `*` matches any suffix; ordered so that more specific patterns win
+    const WINDOWS: &[(&str, u32)] = &[
+        ("gpt-5*", 400_000),
+        ("gpt-4.5*", 128_000),
+        ("gpt-4.1*", 1_047_576),
+        ("gpt-4o*", 128_000),
+        ("chatgpt-4o*", 128_000),
+        ("gpt-4-turbo*", 128_000),
+        ("gpt-4-1106-preview", 128_000),
+        ("gpt-4-0125-preview", 128_000),
+        ("gpt-4-vision-preview", 128_000),
+        ("gpt-4-32k*", 32_768),
+        ("gpt-4", 8_192),
+        ("gpt-4-0613", 8_192),
+        ("gpt-4-0314", 8_192),
+        ("gpt-3.5-turbo-instruct*", 4_096),
+        ("gpt-3.5-turbo*", 16_385),
+        ("gpt-35-turbo*", 16_385),
+        ("o1-mini*", 128_000),
+        ("o1-preview*", 128_000),
+        ("o1*", 200_000),
+        ("o3*", 200_000),
+        ("o4-mini*", 200_000),
+        ("deepseek-chat", 131_072),
+        ("deepseek-reasoner", 131_072),
+        ("qwen-long*", 10_000_000),
+        ("qwen-turbo*", 1_000_000),
+        ("qwen-plus*", 131_072),
+        ("qwen-max*", 32_768),
+        ("qwen3-max*", 262_144),
+        ("qwen3-coder-plus*", 1_048_576),
+        ("qwen3-coder*", 262_144),
+        ("qwen3*", 131_072),
+        ("qwen2.5-turbo*", 1_000_000),
+        ("qwen2.5*", 131_072),
+        ("claude-2*", 100_000),
+        ("claude-instant*", 100_000),
+        ("claude*", 200_000),
+        ("gemini-1.0-pro*", 32_760),
+        ("gemini-pro", 32_760),
+        ("gemini-1.5-pro*", 2_097_152),
+        ("gemini*", 1_048_576),
+    ];
+
+    WINDOWS
+        .iter()
+        .find(|(pattern, _)| match pattern.strip_suffix('*') {
+            Some(prefix) => model.starts_with(prefix),
+            None => model == *pattern,
+        })
+        .map(|(_, window)| *window)
+}
+
+/// Strip a `provider/` prefix and normalize case
+fn model_name(model: &str) -> String {
+    model.rsplit('/').next().unwrap_or(model).to_lowercase()
+}
+
+/// Prompt and completion token budget of a request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TokenBudget {
+    /// Estimated prompt tokens
+    pub prompt_tokens: u32,
+    /// Requested completion tokens (`max_tokens`)
+    pub completion_tokens: u32,
+    /// Context window of the model, if known
+    pub context_window: Option<u32>,
+}
+
+impl TokenBudget {
+    /// Compute the budget of a request
+    pub fn for_request(request: &ChatRequest) -> Self {
+        Self {
+            prompt_tokens: request.estimated_prompt_tokens(),
+            completion_tokens: request.max_tokens.unwrap_or(0),
+            context_window: context_window(&request.model),
+        }
+    }
+
+    /// Whether prompt and completion fit in the context window
+    ///
+    /// Always true when the context window is unknown.
+    pub fn fits(&self) -> bool {
+        self.context_window.is_none_or(|window| {
+            self.prompt_tokens.saturating_add(self.completion_tokens) <= window
+        })
+    }
+
+    /// Completion tokens left after the prompt, if the context window is known
+    pub fn available_completion_tokens(&self) -> Option<u32> {
+        self.context_window
+            .map(|window| window.saturating_sub(self.prompt_tokens))
+    }
+}
@@ -1,6 +1,8 @@
 //! Core types for the LLM Gateway library
 
-use serde::{Deserialize, Serialize};
+use crate::providers::ProviderType;
+use crate::tokenizer::{self, TokenBudget};
+use serde::{Deserialize, Deserializer, Serialize};
 use std::collections::HashMap;
 
 /// Role of a message in a chat conversation
@@ -15,6 +17,8 @@ pub enum MessageRole {
     Assistant,
     /// Function call message
     Function,
+    /// Result of a tool call
+    Tool,
 }
 
 /// A single chat message
@@ -22,7 +26,8 @@ pub enum MessageRole {
 pub struct ChatMessage {
     /// Role of the message sender
     pub role: MessageRole,
-    /// Content of the message
+    /// Content of the message (empty when the assistant only calls tools)
+    #[serde(default, deserialize_with = "null_as_default")]
     pub content: String,
     /// Optional name for the message sender
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -30,6 +35,21 @@ pub struct ChatMessage {
     /// Optional function call data
     #[serde(skip_serializing_if = "Option::is_none")]
     pub function_call: Option<FunctionCall>,
+    /// Tool calls requested by the assistant
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_calls: Option<Vec<ToolCall>>,
+    /// ID of the tool call this message answers (tool role only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_call_id: Option<String>,
+}
+
+/// Deserialize `null` as the type's default value
+fn null_as_default<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Default + Deserialize<'de>,
+{
+    Ok(Option::<T>::deserialize(deserializer)?.unwrap_or_default())
 }
 
 impl ChatMessage {
@@ -40,6 +60,8 @@ impl ChatMessage {
             content: content.into(),
             name: None,
             function_call: None,
+            tool_calls: None,
+            tool_call_id: None,
         }
     }
 
@@ -57,6 +79,33 @@ impl ChatMessage {
     pub fn assistant(content: impl Into<String>) -> Self {
         Self::new(MessageRole::Assistant, content)
     }
+
+    /// Create an assistant message that calls tools
+    pub fn assistant_tool_calls(tool_calls: Vec<ToolCall>) -> Self {
+        Self {
+            tool_calls: Some(tool_calls),
+            ..Self::new(MessageRole::Assistant, "")
+        }
+    }
+
+    /// Create a tool result message answering the tool call `tool_call_id`
+    pub fn tool(tool_call_id: impl Into<String>, content: impl Into<String>) -> Self {
+        Self {
+            tool_call_id: Some(tool_call_id.into()),
+            ..Self::new(MessageRole::Tool, content)
+        }
+    }
+
+    /// Set the name of the message sender
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Tool calls requested by this message, if any
+    pub fn tool_calls(&self) -> &[ToolCall] {
+        self.tool_calls.as_deref().unwrap_or_default()
+    }
 }
 
 /// Function call information
@@ -68,6 +117,128 @@ pub struct FunctionCall {
     pub arguments: String,
 }
 
+/// A tool call requested by the assistant
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolCall {
+    /// Unique identifier of the call, echoed back in the tool result
+    pub id: String,
+    /// Tool type (always "function")
+    #[serde(rename = "type", default = "function_type")]
+    pub kind: String,
+    /// The function to call
+    pub function: FunctionCall,
+}
+
+impl ToolCall {
+    /// Create a function tool call
+    pub fn function(
+        id: impl Into<String>,
+        name: impl Into<String>,
+        arguments: impl Into<String>,
+    ) -> Self {
+        Self {
+            id: id.into(),
+            kind: function_type(),
+            function: FunctionCall {
+                name: name.into(),
+                arguments: arguments.into(),
+            },
+        }
+    }
+}
+
+fn function_type() -> String {
+    "function".to_string()
+}
+
+/// A tool the model may call
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Tool {
+    /// Tool type (always "function")
+    #[serde(rename = "type", default = "function_type")]
+    pub kind: String,
+    /// The function definition
+    pub function: FunctionDefinition,
+}
+
+impl Tool {
+    /// Create a function tool
+    pub fn function(
+        name: impl Into<String>,
+        description: impl Into<String>,
+        parameters: serde_json::Value,
+    ) -> Self {
+        Self {
+            kind: function_type(),
+            function: FunctionDefinition {
+                name: name.into(),
+                description: Some(description.into()),
+                parameters,
+            },
+        }
+    }
+}
+
+/// Declaration of a callable function
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FunctionDefinition {
+    /// Name of the function
+    pub name: String,
+    /// What the function does, used by the model to decide when to call it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// JSON Schema of the function arguments
+    pub parameters: serde_json::Value,
+}
+
+/// Controls whether and which tool the model calls
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ToolChoice {
+    /// `"none"`, `"auto"` or `"required"`
+    Mode(String),
+    /// Force a specific function
+    Named {
+        /// Tool type (always "function")
+        #[serde(rename = "type")]
+        kind: String,
+        /// The function to call
+        function: NamedFunction,
+    },
+}
+
+/// Function selected by [`ToolChoice::Named`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NamedFunction {
+    /// Name of the function
+    pub name: String,
+}
+
+impl ToolChoice {
+    /// Never call tools
+    pub fn none() -> Self {
+        ToolChoice::Mode("none".to_string())
+    }
+
+    /// Let the model decide
+    pub fn auto() -> Self {
+        ToolChoice::Mode("auto".to_string())
+    }
+
+    /// Call at least one tool
+    pub fn required() -> Self {
+        ToolChoice::Mode("required".to_string())
+    }
+
+    /// Call the named function
+    pub fn function(name: impl Into<String>) -> Self {
+        ToolChoice::Named {
+            kind: function_type(),
+            function: NamedFunction { name: name.into() },
+        }
+    }
+}
+
 /// Chat completion request
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ChatRequest {
@@ -84,9 +255,24 @@ pub struct ChatRequest {
     /// Top-p sampling parameter
     #[serde(skip_serializing_if = "Option::is_none")]
     pub top_p: Option<f32>,
+    /// Number of choices to generate
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub n: Option<u32>,
     /// Whether to stream the response
     #[serde(skip_serializing_if = "Option::is_none")]
     pub stream: Option<bool>,
+    /// Tools the model may call
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tools: Option<Vec<Tool>>,
+    /// Controls which tool, if any, is called
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_choice: Option<ToolChoice>,
+    /// Whether the model may call several tools in one turn
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parallel_tool_calls: Option<bool>,
+    /// Required format of the reply
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_format: Option<ResponseFormat>,
     /// Additional provider-specific parameters
     #[serde(flatten)]
     pub extra: HashMap<String, serde_json::Value>,
@@ -101,7 +287,12 @@ impl ChatRequest {
             max_tokens: None,
             temperature: None,
             top_p: None,
+            n: None,
             stream: None,
+            tools: None,
+            tool_choice: None,
+            parallel_tool_calls: None,
+            response_format: None,
             extra: HashMap::new(),
         }
     }
@@ -124,11 +315,113 @@ impl ChatRequest {
         self
     }
 
+    /// Set the number of choices to generate
+    pub fn with_n(mut self, n: u32) -> Self {
+        self.n = Some(n);
+        self
+    }
+
     /// Enable streaming
     pub fn with_stream(mut self, stream: bool) -> Self {
         self.stream = Some(stream);
         self
     }
+
+    /// Set the tools the model may call
+    pub fn with_tools(mut self, tools: Vec<Tool>) -> Self {
+        self.tools = Some(tools);
+        self
+    }
+
+    /// Set the tool choice
+    pub fn with_tool_choice(mut self, tool_choice: ToolChoice) -> Self {
+        self.tool_choice = Some(tool_choice);
+        self
+    }
+
+    /// Allow or forbid parallel tool calls
+    pub fn with_parallel_tool_calls(mut self, parallel: bool) -> Self {
+        self.parallel_tool_calls = Some(parallel);
+        self
+    }
+
+    /// Set the required reply format
+    pub fn with_response_format(mut self, response_format: ResponseFormat) -> Self {
+        self.response_format = Some(response_format);
+        self
+    }
+
+    /// Estimate the number of prompt tokens this request will use
+    pub fn estimated_prompt_tokens(&self) -> u32 {
+        let mut tokens = tokenizer::count_message_tokens(&self.model, &self.messages);
+        if let Some(tools) = &self.tools {
+            let definitions = serde_json::to_string(tools).unwrap_or_default();
+            tokens += tokenizer::count_tokens(&self.model, &definitions);
+        }
+        tokens
+    }
+
+    /// Prompt and completion token budget of this request
+    pub fn token_budget(&self) -> TokenBudget {
+        TokenBudget::for_request(self)
+    }
+}
+
+/// Required format of the model's reply
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ResponseFormat {
+    /// Free-form text (the default)
+    Text,
+    /// Any valid JSON object
+    JsonObject,
+    /// JSON conforming to a schema
+    JsonSchema {
+        /// The schema definition
+        json_schema: JsonSchemaFormat,
+    },
+}
+
+impl ResponseFormat {
+    /// Require JSON conforming to `schema`
+    pub fn json_schema(name: impl Into<String>, schema: serde_json::Value) -> Self {
+        ResponseFormat::JsonSchema {
+            json_schema: JsonSchemaFormat {
+                name: name.into(),
+                description: None,
+                schema,
+                strict: Some(true),
+            },
+        }
+    }
+
+    /// The JSON schema, if any
+    pub fn schema(&self) -> Option<&serde_json::Value> {
+        match self {
+            ResponseFormat::JsonSchema { json_schema } => Some(&json_schema.schema),
+            _ => None,
+        }
+    }
+
+    /// Whether the reply must be JSON
+    pub fn is_json(&self) -> bool {
+        !matches!(self, ResponseFormat::Text)
+    }
+}
+
+/// Named JSON schema for [`ResponseFormat::JsonSchema`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JsonSchemaFormat {
+    /// Name of the schema
+    pub name: String,
+    /// What the schema describes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// The JSON Schema itself
+    pub schema: serde_json::Value,
+    /// Whether the provider should enforce the schema strictly
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub strict: Option<bool>,
 }
 
 /// Usage statistics for a completion
@@ -142,6 +435,34 @@ pub struct Usage {
     pub total_tokens: u32,
 }
 
+/// Model offered by a provider
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModelInfo {
+    /// Name to request the model by (the deployment name for Azure)
+    pub id: String,
+    /// Organization that owns the model
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owned_by: Option<String>,
+    /// Unix timestamp of when the model or deployment was created
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created: Option<u64>,
+    /// Model behind a deployment
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+}
+
+impl ModelInfo {
+    /// Create model information with just a name
+    pub fn new(id: impl Into<String>) -> Self {
+        Self {
+            id: id.into(),
+            owned_by: None,
+            created: None,
+            model: None,
+        }
+    }
+}
+
 /// A single choice in the chat completion response
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ChatChoice {
@@ -171,9 +492,27 @@ pub struct ChatResponse {
     /// System fingerprint
     #[serde(skip_serializing_if = "Option::is_none")]
     pub system_fingerprint: Option<String>,
+    /// Provider that produced the response, set by the gateway
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<ProviderType>,
+    /// Whether the response was served from the gateway's cache
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub cached: bool,
 }
 
 impl ChatResponse {
+    /// Append the choices and usage of `other`, renumbering its choices
+    pub fn merge(&mut self, other: ChatResponse) {
+        let offset = self.choices.len() as u32;
+        for (i, mut choice) in other.choices.into_iter().enumerate() {
+            choice.index = offset + i as u32;
+            self.choices.push(choice);
+        }
+        self.usage.prompt_tokens += other.usage.prompt_tokens;
+        self.usage.completion_tokens += other.usage.completion_tokens;
+        self.usage.total_tokens += other.usage.total_tokens;
+    }
+
     /// Get the content of the first choice
     pub fn content(&self) -> Option<&str> {
         self.choices
@@ -181,8 +520,156 @@ impl ChatResponse {
             .map(|choice| choice.message.content.as_str())
     }
 
+    /// Iterate over the content of every choice, in order
+    pub fn contents(&self) -> impl Iterator<Item = &str> {
+        self.choices
+            .iter()
+            .map(|choice| choice.message.content.as_str())
+    }
+
     /// Get the first choice message
     pub fn message(&self) -> Option<&ChatMessage> {
         self.choices.first().map(|choice| &choice.message)
     }
+
+    /// Get the tool calls requested by the first choice
+    pub fn tool_calls(&self) -> &[ToolCall] {
+        self.message()
+            .map(ChatMessage::tool_calls)
+            .unwrap_or_default()
+    }
+}
+
+/// Incremental message content carried by a streamed chunk
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ChatDelta {
+    /// Role of the message sender (usually only present on the first chunk)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub role: Option<MessageRole>,
+    /// Content fragment
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+    /// Tool call fragments
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_calls: Option<Vec<ToolCallDelta>>,
+}
+
+/// Fragment of a streamed tool call
+///
+/// The first fragment for a given `index` carries the id and function name;
+/// later ones append to the arguments.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolCallDelta {
+    /// Position of the tool call in the message
+    pub index: u32,
+    /// Tool call ID
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    /// Tool type (always "function")
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,
+    /// Function name and argument fragment
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub function: Option<FunctionCallDelta>,
+}
+
+/// Fragment of a streamed function call
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FunctionCallDelta {
+    /// Function name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Fragment of the JSON arguments
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub arguments: Option<String>,
+}
+
+/// A single choice in a streamed chat completion chunk
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatChunkChoice {
+    /// Index of this choice
+    pub index: u32,
+    /// The incremental message content
+    #[serde(default)]
+    pub delta: ChatDelta,
+    /// Reason why the completion finished (only present on the last chunk)
+    pub finish_reason: Option<String>,
+}
+
+/// Streamed chat completion chunk
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatCompletionChunk {
+    /// Unique identifier for the completion
+    #[serde(default)]
+    pub id: String,
+    /// Object type (always "chat.completion.chunk")
+    #[serde(default)]
+    pub object: String,
+    /// Unix timestamp of creation
+    #[serde(default)]
+    pub created: u64,
+    /// Model used for completion
+    #[serde(default)]
+    pub model: String,
+    /// List of choice deltas
+    #[serde(default)]
+    pub choices: Vec<ChatChunkChoice>,
+    /// Usage statistics (only present on the final chunk, if requested)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub usage: Option<Usage>,
+}
+
+impl ChatCompletionChunk {
+    /// Get the content fragment of the first choice
+    pub fn content(&self) -> Option<&str> {
+        self.choices
+            .first()
+            .and_then(|choice| choice.delta.content.as_deref())
+    }
+
+    /// Get the finish reason of the first choice
+    pub fn finish_reason(&self) -> Option<&str> {
+        self.choices
+            .first()
+            .and_then(|choice| choice.finish_reason.as_deref())
+    }
+}
+
+impl From<ChatResponse> for ChatCompletionChunk {
+    fn from(response: ChatResponse) -> Self {
+        Self {
+            id: response.id,
+            object: "chat.completion.chunk".to_string(),
+            created: response.created,
+            model: response.model,
+            choices: response
+                .choices
+                .into_iter()
+                .map(|choice| ChatChunkChoice {
+                    index: choice.index,
+                    delta: ChatDelta {
+                        role: Some(choice.message.role),
+                        content: Some(choice.message.content),
+                        tool_calls: choice.message.tool_calls.map(|calls| {
+                            calls
+                                .into_iter()
+                                .enumerate()
+                                .map(|(index, call)| ToolCallDelta {
+                                    index: index as u32,
+                                    id: Some(call.id),
+                                    kind: Some(call.kind),
+                                    function: Some(FunctionCallDelta {
+                                        name: Some(call.function.name),
+                                        arguments: Some(call.function.arguments),
+                                    }),
+                                })
+                                .collect()
+                        }),
+                    },
+                    finish_reason: choice.finish_reason,
+                })
+                .collect(),
+            usage: Some(response.usage),
+        }
+    }
 }
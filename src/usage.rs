@@ -0,0 +1,314 @@
+//! Persistent usage ledger and cost reporting
+//!
+//! Every completed call is appended as one JSON line to a ledger file under
+//! the user data directory. Costs are not stored; they are computed from a
+//! price table when the ledger is read, so correcting a price fixes old
+//! entries too.
+
+use crate::config;
+use crate::error::{GatewayError, Result};
+use crate::providers::ProviderType;
+use crate::types::Usage;
+use chrono::{Local, TimeZone};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Built-in prices in USD per million tokens (input, output)
+const DEFAULT_PRICES: &[(&str, f64, f64)] = &[
+    ("gpt-5", 1.25, 10.0),
+    ("gpt-5-mini", 0.25, 2.0),
+    ("gpt-5-nano", 0.05, 0.4),
+    ("gpt-4.1", 2.0, 8.0),
+    ("gpt-4.1-mini", 0.4, 1.6),
+    ("gpt-4.1-nano", 0.1, 0.4),
+    ("gpt-4o", 2.5, 10.0),
+    ("gpt-4o-mini", 0.15, 0.6),
+    ("gpt-4-turbo", 10.0, 30.0),
+    ("gpt-4", 30.0, 60.0),
+    ("gpt-3.5-turbo", 0.5, 1.5),
+    ("o1", 15.0, 60.0),
+    ("o1-mini", 1.1, 4.4),
+    ("o3", 2.0, 8.0),
+    ("o3-mini", 1.1, 4.4),
+    ("o4-mini", 1.1, 4.4),
+    ("deepseek-chat", 0.27, 1.1),
+    ("deepseek-reasoner", 0.55, 2.19),
+    ("qwen-turbo", 0.05, 0.2),
+    ("qwen-plus", 0.4, 1.2),
+    ("qwen-max", 1.6, 6.4),
+    ("claude-3-5-haiku", 0.8, 4.0),
+    ("claude-3-5-sonnet", 3.0, 15.0),
+    ("claude-3-7-sonnet", 3.0, 15.0),
+    ("claude-sonnet-4", 3.0, 15.0),
+    ("claude-3-opus", 15.0, 75.0),
+    ("claude-opus-4", 15.0, 75.0),
+    ("gemini-2.0-flash", 0.1, 0.4),
+    ("gemini-2.5-flash", 0.3, 2.5),
+    ("gemini-2.5-pro", 1.25, 10.0),
+    ("gemini-1.5-flash", 0.075, 0.3),
+    ("gemini-1.5-pro", 1.25, 5.0),
+];
+
+/// One completed call
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageRecord {
+    /// Unix timestamp of the call
+    pub timestamp: u64,
+    /// Provider that answered
+    pub provider: String,
+    /// Model that answered
+    pub model: String,
+    /// Prompt tokens reported by the provider
+    pub prompt_tokens: u32,
+    /// Completion tokens reported by the provider
+    pub completion_tokens: u32,
+    /// Wall-clock latency in milliseconds, including retries
+    pub latency_ms: u64,
+    /// Repository the call was made for
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repository: Option<String>,
+}
+
+impl UsageRecord {
+    /// Create a record for a call that just finished
+    pub fn new(provider: &ProviderType, model: &str, usage: &Usage, latency: Duration) -> Self {
+        Self {
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+            provider: provider.to_string(),
+            model: model.to_string(),
+            prompt_tokens: usage.prompt_tokens,
+            completion_tokens: usage.completion_tokens,
+            latency_ms: latency.as_millis() as u64,
+            repository: None,
+        }
+    }
+
+    /// Local calendar day of the call (`YYYY-MM-DD`)
+    pub fn day(&self) -> String {
+        Local
+            .timestamp_opt(self.timestamp as i64, 0)
+            .single()
+            .map(|time| time.format("%Y-%m-%d").to_string())
+            .unwrap_or_default()
+    }
+}
+
+/// Append-only JSONL ledger of calls
+#[derive(Debug, Clone)]
+pub struct UsageLedger {
+    path: PathBuf,
+    repository: Option<String>,
+}
+
+impl UsageLedger {
+    /// Create a ledger stored at `path`
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            repository: None,
+        }
+    }
+
+    /// Tag every record written through this ledger with a repository name
+    pub fn with_repository(mut self, repository: impl Into<String>) -> Self {
+        self.repository = Some(repository.into());
+        self
+    }
+
+    /// Default ledger location (`<data dir>/fuckmit/usage.jsonl`)
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join("fuckmit").join("usage.jsonl"))
+    }
+
+    /// Path of the ledger file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append a record to the ledger
+    pub fn record(&self, mut record: UsageRecord) -> Result<()> {
+        if record.repository.is_none() {
+            record.repository = self.repository.clone();
+        }
+
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let mut line = serde_json::to_string(&record)?;
+        line.push('\n');
+
+        // A single write keeps lines intact when several processes append at once
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        file.write_all(line.as_bytes())?;
+        Ok(())
+    }
+
+    /// Read all records; a missing ledger has no records and malformed lines are skipped
+    pub fn records(&self) -> Result<Vec<UsageRecord>> {
+        let file = match fs::File::open(&self.path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err.into()),
+        };
+
+        let mut records = Vec::new();
+        for line in BufReader::new(file).lines() {
+            if let Ok(record) = serde_json::from_str(&line?) {
+                records.push(record);
+            }
+        }
+        Ok(records)
+    }
+}
+
+/// Price of a model in USD per million tokens
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ModelPrice {
+    /// Price of a million prompt tokens
+    pub input: f64,
+    /// Price of a million completion tokens
+    pub output: f64,
+}
+
+impl ModelPrice {
+    /// Cost of a number of prompt and completion tokens
+    pub fn cost(&self, prompt_tokens: u32, completion_tokens: u32) -> f64 {
+        (prompt_tokens as f64 * self.input + completion_tokens as f64 * self.output) / 1_000_000.0
+    }
+}
+
+/// Model prices, looked up by the longest matching model name prefix
+#[derive(Debug, Clone)]
+pub struct PriceTable {
+    prices: HashMap<String, ModelPrice>,
+}
+
+impl Default for PriceTable {
+    fn default() -> Self {
+        let prices = DEFAULT_PRICES
+            .iter()
+            .map(|(model, input, output)| {
+                (
+                    model.to_string(),
+                    ModelPrice {
+                        input: *input,
+                        output: *output,
+                    },
+                )
+            })
+            .collect();
+        Self { prices }
+    }
+}
+
+impl PriceTable {
+    /// Built-in prices overridden by a TOML file of `"model" = { input = .., output = .. }`
+    pub fn load(path: &Path) -> Result<Self> {
+        let mut table = Self::default();
+        if !path.exists() {
+            return Ok(table);
+        }
+
+        let content = fs::read_to_string(path)?;
+        let overrides: HashMap<String, ModelPrice> = toml::from_str(&content).map_err(|e| {
+            GatewayError::Config(format!("Invalid price table {}: {}", path.display(), e))
+        })?;
+        for (model, price) in overrides {
+            table.prices.insert(model.to_lowercase(), price);
+        }
+        Ok(table)
+    }
+
+    /// Default price table location (`~/.config/fuckmit/prices.toml`)
+    pub fn default_path() -> Option<PathBuf> {
+        config::config_dir().map(|dir| dir.join("prices.toml"))
+    }
+
+    /// Set the price of a model
+    pub fn with_price(mut self, model: impl Into<String>, price: ModelPrice) -> Self {
+        self.prices.insert(model.into().to_lowercase(), price);
+        self
+    }
+
+    /// Price of a model, if known
+    pub fn price(&self, model: &str) -> Option<ModelPrice> {
+        let model = model.rsplit('/').next().unwrap_or(model).to_lowercase();
+        self.prices
+            .iter()
+            .filter(|(prefix, _)| model.starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(_, price)| *price)
+    }
+
+    /// Cost of a record; local Ollama models and the mock are free, unknown models have no cost
+    pub fn cost(&self, record: &UsageRecord) -> Option<f64> {
+        match self.price(&record.model) {
+            Some(price) => Some(price.cost(record.prompt_tokens, record.completion_tokens)),
+            None if record.provider == "ollama" || record.provider == "mock" => Some(0.0),
+            None => None,
+        }
+    }
+}
+
+/// Aggregated usage of a group of calls
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UsageTotals {
+    /// Number of calls
+    pub calls: u64,
+    /// Total prompt tokens
+    pub prompt_tokens: u64,
+    /// Total completion tokens
+    pub completion_tokens: u64,
+    /// Total latency in milliseconds
+    pub latency_ms: u64,
+    /// Total cost of the calls with a known price
+    pub cost: f64,
+    /// Calls whose model has no known price
+    pub unpriced_calls: u64,
+}
+
+impl UsageTotals {
+    /// Add a record to the totals
+    pub fn add(&mut self, record: &UsageRecord, prices: &PriceTable) {
+        self.calls += 1;
+        self.prompt_tokens += u64::from(record.prompt_tokens);
+        self.completion_tokens += u64::from(record.completion_tokens);
+        self.latency_ms += record.latency_ms;
+        match prices.cost(record) {
+            Some(cost) => self.cost += cost,
+            None => self.unpriced_calls += 1,
+        }
+    }
+
+    /// Average latency per call
+    pub fn average_latency(&self) -> Duration {
+        Duration::from_millis(self.latency_ms.checked_div(self.calls).unwrap_or_default())
+    }
+}
+
+/// Total `records` per key, sorted by key
+pub fn totals_by<F>(
+    records: &[UsageRecord],
+    prices: &PriceTable,
+    key: F,
+) -> BTreeMap<String, UsageTotals>
+where
+    F: Fn(&UsageRecord) -> String,
+{
+    let mut totals: BTreeMap<String, UsageTotals> = BTreeMap::new();
+    for record in records {
+        totals.entry(key(record)).or_default().add(record, prices);
+    }
+    totals
+}
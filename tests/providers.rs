@@ -0,0 +1,204 @@
+//! Wire format tests for the built-in providers, replayed from cassettes
+//!
+//! The cassettes in `tests/cassettes` are hand-written fixtures that follow
+//! the documented request and response formats of each API, including its
+//! error bodies. They are not recordings and cannot be re-recorded: a real
+//! API would not return these errors for these requests.
+
+use fuckmit::cassette::{CassetteConfig, CassetteMode};
+use fuckmit::{
+    ChatMessage, ChatRequest, GatewayConfig, GatewayError, LLMGateway, ProviderConfig, ProviderType,
+};
+use futures::StreamExt;
+use std::path::Path;
+
+const PROMPT: &str = "Write a commit message for: add nested table parsing";
+const MESSAGE: &str = "feat(parser): support nested tables";
+
+/// Prompt the error fixtures answer
+const ERROR_PROMPT: &str = "Hello";
+
+const AZURE_ENDPOINT: &str = "https://contoso.openai.azure.com";
+
+/// Replayed requests are matched without their API key
+const API_KEY: &str = "test-key";
+
+/// Gateway for one provider that replays `tests/cassettes/<cassette>.json`
+fn gateway(provider_type: ProviderType, provider: ProviderConfig) -> LLMGateway {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/cassettes")
+        .join(format!("{}.json", provider_type));
+    let config = GatewayConfig::new(provider_type.clone())
+        .with_provider(provider_type, provider)
+        .with_max_retries(0)
+        .with_cassette(CassetteConfig::new(path).with_mode(CassetteMode::Replay));
+    LLMGateway::new(config).expect("gateway")
+}
+
+fn openai() -> LLMGateway {
+    gateway(ProviderType::OpenAI, ProviderConfig::new(API_KEY))
+}
+
+fn azure() -> LLMGateway {
+    gateway(
+        ProviderType::Azure,
+        ProviderConfig::new(API_KEY)
+            .with_base_url(AZURE_ENDPOINT)
+            .with_default_model("gpt-4o-mini"),
+    )
+}
+
+fn deepseek() -> LLMGateway {
+    gateway(ProviderType::DeepSeek, ProviderConfig::new(API_KEY))
+}
+
+fn qwen() -> LLMGateway {
+    gateway(ProviderType::Qwen, ProviderConfig::new(API_KEY))
+}
+
+fn request(prompt: &str, model: &str) -> ChatRequest {
+    ChatRequest::new(vec![ChatMessage::user(prompt)], model).with_max_tokens(100)
+}
+
+async fn complete(gateway: &LLMGateway, model: &str) {
+    let response = gateway.chat_request(request(PROMPT, model)).await.unwrap();
+    assert_eq!(response.content(), Some(MESSAGE));
+    assert_eq!(response.choices[0].finish_reason.as_deref(), Some("stop"));
+    assert!(response.usage.total_tokens > 0);
+}
+
+async fn stream(gateway: &LLMGateway, model: &str) {
+    let mut stream = gateway
+        .chat_request_stream(request(PROMPT, model))
+        .await
+        .unwrap();
+
+    let mut content = String::new();
+    let mut finish_reason = None;
+    let mut usage = None;
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.unwrap();
+        content.push_str(chunk.content().unwrap_or_default());
+        finish_reason = chunk.finish_reason().map(str::to_string).or(finish_reason);
+        usage = chunk.usage.or(usage);
+    }
+
+    assert_eq!(content, MESSAGE);
+    assert_eq!(finish_reason.as_deref(), Some("stop"));
+    assert!(usage.is_some_and(|usage| usage.total_tokens > 0));
+}
+
+async fn error(gateway: &LLMGateway, model: &str) -> GatewayError {
+    gateway
+        .chat_request(request(ERROR_PROMPT, model))
+        .await
+        .unwrap_err()
+}
+
+async fn model_ids(gateway: &LLMGateway, provider_type: ProviderType) -> Vec<String> {
+    gateway
+        .list_models_with_provider(&provider_type)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|model| model.id)
+        .collect()
+}
+
+#[tokio::test]
+async fn openai_chat_completion() {
+    complete(&openai(), "gpt-4o-mini").await;
+}
+
+#[tokio::test]
+async fn openai_stream() {
+    stream(&openai(), "gpt-4o-mini").await;
+}
+
+#[tokio::test]
+async fn openai_invalid_key() {
+    let err = error(&openai(), "gpt-4o-mini").await;
+    assert!(matches!(err, GatewayError::Unauthorized(_)), "{:?}", err);
+    assert_eq!(
+        err.api_error().unwrap().code.as_deref(),
+        Some("invalid_api_key")
+    );
+}
+
+#[tokio::test]
+async fn openai_models() {
+    let ids = model_ids(&openai(), ProviderType::OpenAI).await;
+    assert_eq!(ids, ["gpt-4o", "gpt-4o-mini"]);
+}
+
+#[tokio::test]
+async fn azure_chat_completion() {
+    complete(&azure(), "gpt-4o-mini").await;
+}
+
+#[tokio::test]
+async fn azure_stream() {
+    // The first chunk only carries prompt filter results
+    stream(&azure(), "gpt-4o-mini").await;
+}
+
+#[tokio::test]
+async fn azure_content_filter() {
+    let err = error(&azure(), "gpt-4o-mini").await;
+    assert!(matches!(err, GatewayError::ContentFiltered(_)), "{:?}", err);
+}
+
+#[tokio::test]
+async fn azure_models() {
+    let ids = model_ids(&azure(), ProviderType::Azure).await;
+    assert_eq!(ids, ["gpt-4o-mini"]);
+}
+
+#[tokio::test]
+async fn deepseek_chat_completion() {
+    complete(&deepseek(), "deepseek-chat").await;
+}
+
+#[tokio::test]
+async fn deepseek_stream() {
+    // Includes a keep-alive comment before the first event
+    stream(&deepseek(), "deepseek-chat").await;
+}
+
+#[tokio::test]
+async fn deepseek_context_length() {
+    let err = error(&deepseek(), "deepseek-chat").await;
+    assert!(
+        matches!(err, GatewayError::ContextLengthExceeded(_)),
+        "{:?}",
+        err
+    );
+}
+
+#[tokio::test]
+async fn deepseek_models() {
+    let ids = model_ids(&deepseek(), ProviderType::DeepSeek).await;
+    assert_eq!(ids, ["deepseek-chat", "deepseek-reasoner"]);
+}
+
+#[tokio::test]
+async fn qwen_chat_completion() {
+    complete(&qwen(), "qwen-plus").await;
+}
+
+#[tokio::test]
+async fn qwen_stream() {
+    stream(&qwen(), "qwen-plus").await;
+}
+
+#[tokio::test]
+async fn qwen_content_filter() {
+    let err = error(&qwen(), "qwen-plus").await;
+    assert!(matches!(err, GatewayError::ContentFiltered(_)), "{:?}", err);
+}
+
+#[tokio::test]
+async fn qwen_models() {
+    let ids = model_ids(&qwen(), ProviderType::Qwen).await;
+    assert_eq!(ids, ["qwen-plus", "qwen-turbo"]);
+}